@@ -0,0 +1,137 @@
+//! The DataFusion `describe` of the bundled users.ndjson, computing every statistic in a single
+//! aggregation, compared with the plan it replaced: one aggregation per statistic, unioned, which
+//! reads the file once per statistic.
+//!
+//! Run with `cargo bench --bench describe`.
+
+use std::sync::Arc;
+
+use clap::Parser;
+use criterion::{criterion_group, criterion_main, Criterion};
+use datafusion::{
+    arrow::datatypes::{DataType, Field},
+    dataframe::DataFrame,
+    functions::expr_fn::length,
+    functions_aggregate::{
+        average::avg,
+        count::count,
+        median::median,
+        min_max::{max, min},
+        stddev::stddev,
+        sum::sum,
+    },
+    functions_array::length::array_length,
+    logical_expr::{case, cast, col, is_null, lit, Expr},
+    prelude::{NdJsonReadOptions, SessionContext},
+};
+use taotie::{DataFrameDescriber, ReplCommand};
+use tokio::runtime::Runtime;
+
+const STATS: &[&str] = &["count", "nulls", "min", "max", "mean", "stddev", "median"];
+
+/// The old plan: every column turned into a number (lengths for strings and lists), then one
+/// aggregation per statistic, each cast to Float64 and tagged with its position, unioned and
+/// sorted back in order.
+fn union_plan(df: DataFrame) -> anyhow::Result<DataFrame> {
+    let fields: Vec<_> = df.schema().fields().iter().cloned().collect();
+    let transformed = df.select(
+        fields
+            .iter()
+            .map(|f| {
+                let c = col(f.name());
+                let expr = match f.data_type() {
+                    dt if dt.is_temporal() => cast(c, DataType::Float64),
+                    dt if dt.is_numeric() => c,
+                    DataType::List(_) | DataType::LargeList(_) => array_length(c),
+                    _ => length(cast(c, DataType::Utf8)),
+                };
+                expr.alias(f.name())
+            })
+            .collect(),
+    )?;
+
+    let mut ret: Option<DataFrame> = None;
+    for (i, name) in STATS.iter().enumerate() {
+        let exprs = fields
+            .iter()
+            .map(|f| stat(name, col(f.name())).alias(f.name()))
+            .collect();
+        let mut select = vec![lit(*name).alias("describe"), lit(i as u32).alias("order")];
+        select.extend(
+            fields
+                .iter()
+                .map(|f| cast(col(f.name()), DataType::Float64).alias(f.name())),
+        );
+        let df = transformed
+            .clone()
+            .aggregate(vec![], exprs)?
+            .select(select)?;
+        ret = Some(match ret {
+            Some(acc) => acc.union(df)?,
+            None => df,
+        });
+    }
+    let describe = Arc::new(Field::new("describe", DataType::Utf8, false));
+    let columns: Vec<_> = std::iter::once(&describe)
+        .chain(&fields)
+        .map(|f| col(f.name()))
+        .collect();
+    Ok(ret
+        .expect("there are statistics")
+        .sort(vec![col("order").sort(true, false)])?
+        .select(columns)?)
+}
+
+fn stat(name: &str, c: Expr) -> Expr {
+    match name {
+        "count" => count(c),
+        "nulls" => sum(case(is_null(c))
+            .when(lit(true), lit(1))
+            .otherwise(lit(0))
+            .expect("case has a when")),
+        "min" => min(c),
+        "max" => max(c),
+        "mean" => avg(c),
+        "stddev" => stddev(c),
+        "median" => median(c),
+        _ => unreachable!("not one of STATS"),
+    }
+}
+
+fn describe(c: &mut Criterion) {
+    let rt = Runtime::new().expect("Failed to create runtime");
+    let ctx = SessionContext::new();
+    let users = || async {
+        let opts = NdJsonReadOptions::default().file_extension("ndjson");
+        ctx.read_json("assets/users.ndjson", opts).await
+    };
+    let args = ["", "describe", "users", "--stats", &STATS.join(",")];
+    let ReplCommand::Describe(opts) = ReplCommand::parse_from(args) else {
+        unreachable!("a describe command");
+    };
+
+    let mut group = c.benchmark_group("describe");
+    group.bench_function("single_pass", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                let describer = DataFrameDescriber::try_new(users().await?, &opts)?;
+                describer.describe().await?.collect().await?;
+                anyhow::Ok(())
+            })
+            .expect("describe succeeds")
+        })
+    });
+    group.bench_function("union_per_stat", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                union_plan(users().await?)?.collect().await?;
+                anyhow::Ok(())
+            })
+            .expect("describe succeeds")
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, describe);
+criterion_main!(benches);
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{ArrayRef, AsArray, Float64Array, RecordBatch, StringArray},
+    compute::cast as cast_array,
+    datatypes::{DataType, Field, Float64Type, Schema},
+};
+use datafusion::{
+    dataframe::DataFrame,
+    functions_aggregate::{correlation, count::count_udaf},
+    functions_window::expr_fn::rank,
+    logical_expr::{
+        cast, col, expr::WindowFunction, lit, Expr, ExprFunctionExt, WindowFunctionDefinition,
+    },
+    prelude::SessionContext,
+};
+
+use crate::cli::corr::{matrix, pairs, CorrMethod, CorrOps};
+
+pub(super) async fn corr(ctx: &SessionContext, opts: &CorrOps) -> anyhow::Result<DataFrame> {
+    let df = ctx.sql(&format!("SELECT * FROM {}", opts.name)).await?;
+    let available: Vec<_> = df
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| {
+            (
+                f.name().as_str(),
+                is_temporal(f.data_type()) || f.data_type().is_numeric(),
+            )
+        })
+        .collect();
+    let columns = opts.selected_columns(&available)?;
+
+    // temporal values are correlated as their number of days or time units since the epoch
+    let mut values = Vec::new();
+    for (i, name) in columns.iter().enumerate() {
+        let data_type = df.schema().field_with_unqualified_name(name)?.data_type();
+        let value = match is_temporal(data_type) {
+            true => cast(col(name), DataType::Int64),
+            false => col(name),
+        };
+        values.push(cast(value, DataType::Float64).alias(format!("v{i}")));
+    }
+    let df = df.select(values)?;
+    let value = |i: usize| col(format!("v{i}"));
+
+    let pairs = pairs(columns.len());
+    let mut coefficients = Vec::new();
+    if opts.method == CorrMethod::Spearman {
+        // each pair ranks only the rows where both values are set, like the Pearson coefficient
+        for (i, j) in pairs {
+            let both = value(i).is_not_null().and(value(j).is_not_null());
+            let ranks = df.clone().filter(both)?.select(vec![
+                average_rank(value(i))?.alias("x"),
+                average_rank(value(j))?.alias("y"),
+            ])?;
+            let exprs = vec![correlation::corr(col("x"), col("y")).alias("c")];
+            coefficients.extend(first_row(ranks.aggregate(vec![], exprs)?).await?);
+        }
+    } else if !pairs.is_empty() {
+        let exprs = pairs
+            .iter()
+            .enumerate()
+            .map(|(p, (i, j))| correlation::corr(value(*i), value(*j)).alias(format!("c{p}")))
+            .collect();
+        coefficients = first_row(df.aggregate(vec![], exprs)?).await?;
+    }
+
+    let mut fields = vec![Field::new("column", DataType::Utf8, false)];
+    let mut arrays: Vec<ArrayRef> = vec![Arc::new(StringArray::from(columns.clone()))];
+    for (name, values) in columns.iter().zip(matrix(columns.len(), &coefficients)) {
+        fields.push(Field::new(name, DataType::Float64, true));
+        arrays.push(Arc::new(Float64Array::from(values)));
+    }
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?;
+    Ok(SessionContext::new().read_batch(batch)?)
+}
+
+/// The values of the single row of an aggregation, as floats.
+async fn first_row(df: DataFrame) -> anyhow::Result<Vec<Option<f64>>> {
+    let batches = df.collect().await?;
+    let mut values = Vec::new();
+    for column in batches[0].columns() {
+        let column = cast_array(column, &DataType::Float64)?;
+        values.push(column.as_primitive::<Float64Type>().iter().next().flatten());
+    }
+    Ok(values)
+}
+
+fn is_temporal(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Date32 | DataType::Date64 | DataType::Timestamp(..)
+    )
+}
+
+/// The rank of `value` among the rows, ties getting their average rank.
+fn average_rank(value: Expr) -> anyhow::Result<Expr> {
+    let first = rank()
+        .order_by(vec![value.clone().sort(true, false)])
+        .build()?;
+    let ties = Expr::WindowFunction(WindowFunction::new(
+        WindowFunctionDefinition::AggregateUDF(count_udaf()),
+        vec![lit(1)],
+    ))
+    .partition_by(vec![value])
+    .build()?;
+    Ok(cast(first, DataType::Float64) + (cast(ties, DataType::Float64) - lit(1.0)) / lit(2.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn spearman_should_rank_ties() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.sql("CREATE VIEW t AS SELECT * FROM (VALUES (1, 10, 'a'), (2, 20, 'b'), (2, 20, 'c'), (3, NULL, 'd'), (4, 40, 'e')) AS v(x, y, z)")
+            .await?;
+        let opts = CorrOps::new("t".to_string(), vec![], CorrMethod::Spearman, None);
+
+        let batches = corr(&ctx, &opts).await?.collect().await?;
+        let batch = &batches[0];
+        assert_eq!(batch.num_columns(), 3);
+        // the row with a null y doesn't count, so the ranks of the others agree once renumbered
+        let x = batch.column(1).as_primitive::<Float64Type>();
+        assert!(x.values().iter().all(|v| (v - 1.0).abs() < 1e-9));
+
+        Ok(())
+    }
+}
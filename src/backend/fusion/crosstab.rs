@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray, StringArray},
+    compute::cast as cast_array,
+    datatypes::{DataType, Field, Int64Type, Schema},
+    record_batch::RecordBatch,
+};
+use datafusion::{
+    dataframe::DataFrame,
+    functions_aggregate::count::count,
+    functions_window::expr_fn::dense_rank,
+    logical_expr::{cast, col, lit, Expr, ExprFunctionExt},
+    prelude::SessionContext,
+};
+
+use crate::cli::crosstab::{CrosstabCell, CrosstabOps};
+
+pub(super) async fn crosstab(
+    ctx: &SessionContext,
+    opts: &CrosstabOps,
+) -> anyhow::Result<DataFrame> {
+    let df = ctx.sql(&format!("SELECT * FROM {}", opts.name)).await?;
+    let names: Vec<_> = df
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| f.name().as_str())
+        .collect();
+    opts.check_columns(&names)?;
+
+    let (row, column) = (col(&opts.row), col(&opts.column));
+    // the values are ranked in their own type, so that numbers aren't sorted as text
+    let rank = |e: Expr| -> anyhow::Result<Expr> {
+        Ok(dense_rank().order_by(vec![e.sort(true, false)]).build()?)
+    };
+    let batches = df
+        .aggregate(
+            vec![row.clone(), column.clone()],
+            vec![count(lit(1)).alias("count")],
+        )?
+        .select(vec![
+            cast(row.clone(), DataType::Utf8).alias("row"),
+            rank(row)?.alias("row_rank"),
+            cast(column.clone(), DataType::Utf8).alias("column"),
+            rank(column)?.alias("column_rank"),
+            cast(col("count"), DataType::Int64).alias("count"),
+        ])?
+        .collect()
+        .await?;
+
+    let mut cells = Vec::new();
+    for batch in &batches {
+        let rank = |i: usize| cast_array(batch.column(i), &DataType::Int64);
+        let (row_rank, column_rank) = (rank(1)?, rank(3)?);
+        let rows = batch.column(0).as_string::<i32>();
+        let columns = batch.column(2).as_string::<i32>();
+        let counts = batch.column(4).as_primitive::<Int64Type>();
+        let row_ranks = row_rank.as_primitive::<Int64Type>();
+        let column_ranks = column_rank.as_primitive::<Int64Type>();
+        for i in 0..batch.num_rows() {
+            cells.push(CrosstabCell {
+                row: rows.is_valid(i).then(|| rows.value(i).to_string()),
+                row_rank: Some(row_ranks.value(i)),
+                column: columns.is_valid(i).then(|| columns.value(i).to_string()),
+                column_rank: Some(column_ranks.value(i)),
+                count: counts.value(i) as u64,
+            });
+        }
+    }
+    let table = opts.table(cells)?;
+
+    let fields: Vec<_> = table
+        .header
+        .iter()
+        .map(|name| Field::new(name, DataType::Utf8, false))
+        .collect();
+    let arrays: Vec<ArrayRef> = table
+        .columns
+        .into_iter()
+        .map(|values| Arc::new(StringArray::from(values)) as ArrayRef)
+        .collect();
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?;
+    Ok(SessionContext::new().read_batch(batch)?)
+}
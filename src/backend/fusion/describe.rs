@@ -1,187 +1,276 @@
-use std::{fmt, sync::Arc};
+use std::sync::Arc;
 
-use arrow::datatypes::{DataType, Field};
+use arrow::{
+    array::{new_null_array, ArrayRef, AsArray, RecordBatch, StringArray},
+    compute::{cast as cast_array, concat_batches, interleave},
+    datatypes::{DataType, Field, FieldRef, Int64Type, Schema, TimeUnit},
+};
 use datafusion::{
     dataframe::DataFrame,
     functions::expr_fn::length,
     functions_aggregate::{
-        average::avg as f_avg,
-        count::count as f_count,
-        median::median as f_median,
-        min_max::{max as f_max, min as f_min},
-        stddev::stddev as f_stddev,
-        sum::sum as f_sum,
+        approx_distinct::approx_distinct,
+        approx_percentile_cont::approx_percentile_cont,
+        average::avg,
+        count::{count, count_distinct},
+        median::median,
+        min_max::{max, min},
+        stddev::stddev,
+        sum::sum,
     },
     functions_array::length::array_length,
-    // prelude::{cast, col},
-    logical_expr::{case, cast, col, is_null, lit},
+    logical_expr::{case, cast, col, is_null, lit, not, Expr},
+    prelude::SessionContext,
 };
 
-#[allow(unused)]
-#[derive(Debug)]
-pub enum DescribeMethod {
-    Total,
-    NullTotal,
-    Min,
-    Max,
-    Mean,
-    Stddev,
-    Median,
-    // Percentile(u8),
-}
+use super::{
+    element::{element_max, element_mean, element_min},
+    mode::{mode, top_values},
+};
+use crate::cli::describe::{
+    describe_rows, format_span, ColumnKind, DescribeMethod, DescribeOps, DescribeStat,
+};
 
-#[allow(unused)]
 pub struct DataFrameDescriber {
-    original: DataFrame,
-    transformed: DataFrame,
+    /// The `by` columns first, then the described ones.
+    df: DataFrame,
+    by: Vec<String>,
     methods: Vec<DescribeMethod>,
 }
 
-#[allow(unused)]
 impl DataFrameDescriber {
-    pub fn try_new(df: DataFrame) -> anyhow::Result<Self> {
-        let fields = df.schema().fields().iter();
-        // change all temporal columns to Float64
-        let expressions = fields
-            .map(|field| {
-                let dt = field.data_type();
-                let expr = match dt {
-                    dt if dt.is_temporal() => cast(col(field.name()), DataType::Float64),
-                    dt if dt.is_numeric() => col(field.name()),
-                    DataType::List(_) | DataType::LargeList(_) => array_length(col(field.name())),
-                    _ => length(cast(col(field.name()), DataType::Utf8)),
-                };
-                expr.alias(field.name())
-            })
+    pub fn try_new(df: DataFrame, opts: &DescribeOps) -> anyhow::Result<Self> {
+        let names: Vec<_> = df
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().as_str())
             .collect();
-
-        let transformed = df.clone().select(expressions)?;
+        opts.check_columns(&names)?;
+        let selected: Vec<_> = opts
+            .selected_columns(&names)
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let df = df.select_columns(&selected.iter().map(String::as_str).collect::<Vec<_>>())?;
 
         Ok(Self {
-            original: df,
-            transformed,
-            methods: vec![
-                DescribeMethod::Total,
-                DescribeMethod::NullTotal,
-                DescribeMethod::Min,
-                DescribeMethod::Max,
-                DescribeMethod::Mean,
-                DescribeMethod::Stddev,
-                DescribeMethod::Median,
-            ],
+            df,
+            by: opts.by.clone(),
+            methods: opts.methods(),
         })
     }
 
+    /// Compute every statistic of every column in a single aggregation, so the source is only
+    /// scanned once, then pivot the result rows, one per group, into one row per statistic.
     pub async fn describe(&self) -> anyhow::Result<DataFrame> {
-        let df = self.do_describe().await?;
-        self.cast_back(df)
+        let fields = &self.df.schema().fields()[self.by.len()..];
+        let kinds: Vec<_> = fields.iter().map(|f| kind(f.data_type())).collect();
+        let rows = describe_rows(&self.methods, &kinds);
+
+        let mut exprs = Vec::new();
+        let mut ratios = Vec::new();
+        for (field, kind) in fields.iter().zip(&kinds) {
+            for stat in kind.stats(&self.methods) {
+                // an aggregation can't take the difference of two aggregates, the span is taken
+                // from the earliest and latest values when pivoting
+                if stat == DescribeStat::Span {
+                    continue;
+                }
+                let i = rows
+                    .iter()
+                    .position(|r| *r == stat)
+                    .expect("rows cover every stat");
+                let name = format!("{i}_{}", field.name());
+                // nor divide two aggregates, the ratio is taken right after the aggregation
+                if stat == DescribeStat::Method(DescribeMethod::DistinctRatio) {
+                    let c = col(field.name());
+                    exprs.push(count_distinct(c.clone()).alias(format!("{name}_distinct")));
+                    exprs.push(count(c).alias(format!("{name}_count")));
+                    ratios.push(name);
+                    continue;
+                }
+                let expr = self::stat(stat, *kind, col(field.name()));
+                exprs.push(expr.alias(name));
+            }
+        }
+        let by: Vec<_> = self.by.iter().map(col).collect();
+        let sort = by.iter().map(|c| c.clone().sort(true, true)).collect();
+        let mut df = self.df.clone().aggregate(by, exprs)?;
+        for name in ratios {
+            let (distinct, count) = (
+                col(format!("{name}_distinct")),
+                col(format!("{name}_count")),
+            );
+            let ratio = cast(distinct, DataType::Float64) / cast(count, DataType::Float64);
+            df = df.with_column(&name, ratio)?;
+        }
+        let df = df.sort(sort)?;
+        let groups_schema = Arc::new(df.schema().as_arrow().clone());
+        let groups = concat_batches(&groups_schema, &df.collect().await?)?;
+
+        let batch = pivot(&groups, self.by.len(), &rows, fields, &kinds)?;
+        Ok(SessionContext::new().read_batch(batch)?)
     }
+}
 
-    async fn do_describe(&self) -> anyhow::Result<DataFrame> {
-        let df: Option<DataFrame> = self.methods.iter().fold(None, |acc, method| {
-            let df = self.transformed.clone();
-            let stat_df = match method {
-                DescribeMethod::Total => total(df).unwrap(),
-                DescribeMethod::NullTotal => null_total(df).unwrap(),
-                DescribeMethod::Min => min(df).unwrap(),
-                DescribeMethod::Max => max(df).unwrap(),
-                DescribeMethod::Mean => mean(df).unwrap(),
-                DescribeMethod::Stddev => stddev(df).unwrap(),
-                DescribeMethod::Median => median(df).unwrap(),
-                // _ => todo!(),
-            };
-            let mut select_expr = vec![lit(method.to_string()).alias("describe")];
-            select_expr.extend(stat_df.schema().fields().iter().map(|f| col(f.name())));
-
-            let stat_df = stat_df.select(select_expr).unwrap();
-
-            Some(match acc {
-                Some(acc) => acc.union(stat_df).unwrap(),
-                None => stat_df,
-            })
-        });
-        df.ok_or_else(|| anyhow::anyhow!("No statistics found"))
+/// Turn the rows of `stat x column` values, one per group, into the `by` columns, a `describe`
+/// column and one column per described column: Float64 for numeric columns, text for the others
+/// as their rows mix values, lengths and counts.
+fn pivot(
+    groups: &RecordBatch,
+    by: usize,
+    rows: &[DescribeStat],
+    fields: &[FieldRef],
+    kinds: &[ColumnKind],
+) -> anyhow::Result<RecordBatch> {
+    // every statistic of the first group, then every statistic of the next one...
+    let indices: Vec<_> = (0..groups.num_rows())
+        .flat_map(|g| (0..rows.len()).map(move |r| (r, g)))
+        .collect();
+    let group_indices: Vec<_> = indices.iter().map(|(_, g)| (0, *g)).collect();
+
+    let mut out_fields = Vec::new();
+    let mut columns = Vec::new();
+    for (field, keys) in groups.schema().fields()[..by].iter().zip(groups.columns()) {
+        out_fields.push(field.as_ref().clone());
+        columns.push(interleave(&[keys.as_ref()], &group_indices)?);
     }
+    let labels: Vec<_> = indices.iter().map(|(r, _)| rows[*r].to_string()).collect();
+    out_fields.push(Field::new("describe", DataType::Utf8, false));
+    columns.push(Arc::new(StringArray::from(labels)));
 
-    fn cast_back(&self, df: DataFrame) -> anyhow::Result<DataFrame> {
-        // we need the describe column
-        let describe = Arc::new(Field::new("describe", DataType::Utf8, false));
-        let mut fields = vec![&describe];
-        fields.extend(self.original.schema().fields().iter());
-        let expressions = fields
-            .into_iter()
-            .map(|field| {
-                let dt = field.data_type();
-                let expr = match dt {
-                    dt if dt.is_temporal() => cast(col(field.name()), dt.clone()),
-                    DataType::List(_) | DataType::LargeList(_) => {
-                        cast(col(field.name()), DataType::Int32)
+    for (field, kind) in fields.iter().zip(kinds) {
+        let dt = match kind {
+            ColumnKind::Numeric => DataType::Float64,
+            _ => DataType::Utf8,
+        };
+        let cell = |stat: DescribeStat, dt: &DataType| {
+            let i = rows.iter().position(|r| *r == stat);
+            let name = format!("{}_{}", i.unwrap_or_default(), field.name());
+            match (i, groups.column_by_name(&name)) {
+                (Some(_), Some(values)) => anyhow::Ok(Some(cast_array(values, dt)?)),
+                _ => Ok(None),
+            }
+        };
+        let stats = rows
+            .iter()
+            .map(|stat| {
+                let values = match stat {
+                    DescribeStat::Span => {
+                        let ts = DataType::Timestamp(TimeUnit::Millisecond, None);
+                        span(
+                            cell(DescribeStat::Earliest, &ts)?,
+                            cell(DescribeStat::Latest, &ts)?,
+                        )?
                     }
-                    _ => col(field.name()),
+                    _ => cell(*stat, &dt)?,
                 };
-                expr.alias(field.name())
+                anyhow::Ok(values.unwrap_or_else(|| new_null_array(&dt, groups.num_rows())))
             })
-            .collect();
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let stats: Vec<_> = stats.iter().map(|s| s.as_ref()).collect();
+        out_fields.push(Field::new(field.name(), dt, true));
+        columns.push(interleave(&stats, &indices)?);
+    }
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(out_fields)),
+        columns,
+    )?)
+}
 
-        Ok(df
-            .select(expressions)?
-            .sort(vec![col("describe").sort(true, false)])?)
+/// The formatted spans between millisecond timestamps.
+fn span(earliest: Option<ArrayRef>, latest: Option<ArrayRef>) -> anyhow::Result<Option<ArrayRef>> {
+    let (Some(earliest), Some(latest)) = (earliest, latest) else {
+        return Ok(None);
+    };
+    let (earliest, latest) = (
+        cast_array(&earliest, &DataType::Int64)?,
+        cast_array(&latest, &DataType::Int64)?,
+    );
+    let spans: StringArray = earliest
+        .as_primitive::<Int64Type>()
+        .iter()
+        .zip(latest.as_primitive::<Int64Type>())
+        .map(|(start, end)| Some(format_span((end? - start?) as f64 / 1000.0)))
+        .collect();
+    Ok(Some(Arc::new(spans)))
+}
+
+fn kind(dt: &DataType) -> ColumnKind {
+    match dt {
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => ColumnKind::String,
+        DataType::List(f) | DataType::LargeList(f) => ColumnKind::List {
+            numeric: f.data_type().is_numeric(),
+        },
+        DataType::Boolean => ColumnKind::Boolean,
+        DataType::Date32 | DataType::Date64 | DataType::Timestamp(_, _) => ColumnKind::Temporal,
+        dt if dt.is_numeric() => ColumnKind::Numeric,
+        _ => ColumnKind::Other,
     }
 }
 
-impl fmt::Display for DescribeMethod {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            DescribeMethod::Total => write!(f, "Total"),
-            DescribeMethod::NullTotal => write!(f, "NullTotal"),
-            DescribeMethod::Min => write!(f, "Min"),
-            DescribeMethod::Max => write!(f, "Max"),
-            DescribeMethod::Mean => write!(f, "Mean"),
-            DescribeMethod::Stddev => write!(f, "Stddev"),
-            DescribeMethod::Median => write!(f, "Median"),
+/// The expression computing `stat` for a column of the given kind. Only the stats of
+/// `ColumnKind::stats` are asked for, so lengths are only taken of strings and lists.
+fn stat(stat: DescribeStat, kind: ColumnKind, c: Expr) -> Expr {
+    let len = || match kind {
+        ColumnKind::List { .. } => array_length(c.clone()),
+        _ => length(c.clone()),
+    };
+    match stat {
+        DescribeStat::Method(DescribeMethod::ApproxDistinct) if !kind.approx_distinct() => {
+            count_distinct(c)
         }
+        DescribeStat::Method(method) => aggregate(method, c),
+        DescribeStat::MinLength => min(len()),
+        DescribeStat::MaxLength => max(len()),
+        DescribeStat::AvgLength => avg(len()),
+        DescribeStat::Empty => count_true(c.eq(lit(""))),
+        DescribeStat::Top => top_values(c),
+        DescribeStat::ElementMin => element_min(c),
+        DescribeStat::ElementMax => element_max(c),
+        DescribeStat::ElementMean => element_mean(c),
+        DescribeStat::True => count_true(c),
+        DescribeStat::False => count_true(not(c)),
+        DescribeStat::Earliest => min(c),
+        DescribeStat::Latest => max(c),
+        DescribeStat::Span => unreachable!("spans are computed when pivoting"),
     }
 }
 
-macro_rules! describe_method {
-    ($name: ident, $method:ident) => {
-        fn $name(df: DataFrame) -> anyhow::Result<DataFrame> {
-            let original_schema_fields = df.schema().fields().iter();
-            let ret = df.clone().aggregate(
-                vec![],
-                original_schema_fields
-                    .map(|f| $method(col(f.name())).alias(f.name()))
-                    .collect::<Vec<_>>(),
-            )?;
-            Ok(ret)
+fn aggregate(method: DescribeMethod, c: Expr) -> Expr {
+    match method {
+        DescribeMethod::Count => count(c),
+        DescribeMethod::Nulls => count_true(is_null(c)),
+        // the mean of 1 for nulls and 0 for values
+        DescribeMethod::NullRatio => avg(case(is_null(c))
+            .when(lit(true), lit(1.0))
+            .otherwise(lit(0.0))
+            .expect("case has a when")),
+        DescribeMethod::Min => min(c),
+        DescribeMethod::Max => max(c),
+        DescribeMethod::Mean => avg(c),
+        DescribeMethod::Stddev => stddev(c),
+        DescribeMethod::Median => median(c),
+        // DataFusion only has approximate percentiles
+        DescribeMethod::Percentile(p) => {
+            approx_percentile_cont(cast(c, DataType::Float64), lit(p as f64 / 100.0), None)
         }
-    };
+        DescribeMethod::Distinct => count_distinct(c),
+        DescribeMethod::DistinctRatio => {
+            unreachable!("distinct ratios are computed after the aggregation")
+        }
+        DescribeMethod::ApproxDistinct => approx_distinct(c),
+        DescribeMethod::Mode => mode(c),
+    }
 }
 
-describe_method!(total, f_count);
-describe_method!(mean, f_avg);
-describe_method!(stddev, f_stddev);
-describe_method!(max, f_max);
-describe_method!(min, f_min);
-describe_method!(median, f_median);
-
-fn null_total(df: DataFrame) -> anyhow::Result<DataFrame> {
-    let original_schema_fields = df.schema().fields().iter();
-    let ret = df.clone().aggregate(
-        vec![],
-        original_schema_fields
-            .map(|f| {
-                f_sum(
-                    case(is_null(col(f.name())))
-                        .when(lit(true), lit(1))
-                        .otherwise(lit(0))
-                        .unwrap(),
-                )
-                .alias(f.name())
-            })
-            .collect::<Vec<_>>(),
-    )?;
-    Ok(ret)
+/// How many rows `predicate` is true for.
+fn count_true(predicate: Expr) -> Expr {
+    sum(case(predicate)
+        .when(lit(true), lit(1))
+        .otherwise(lit(0))
+        .expect("case has a when"))
 }
 
 #[cfg(test)]
@@ -196,9 +285,49 @@ mod test {
         let df = ctx
             .read_csv("assets/person.csv", CsvReadOptions::default())
             .await?;
-        let dfd = DataFrameDescriber::try_new(df)?;
+        let opts = DescribeOps::new(
+            Some("person".to_string()),
+            None,
+            vec![],
+            vec![],
+            vec![],
+            None,
+        );
+        let dfd = DataFrameDescriber::try_new(df, &opts)?;
+
+        let batches = dfd.describe().await?.collect().await?;
+        // the 8 default stats, then the profile of the string columns
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 15);
+        assert_eq!(batches[0].schema().field(2).data_type(), &DataType::Float64);
+        assert_eq!(batches[0].schema().field(1).data_type(), &DataType::Utf8);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn data_frame_describer_should_describe_groups() -> Result<()> {
+        let ctx = SessionContext::new();
+        let df = ctx
+            .read_csv("assets/person.csv", CsvReadOptions::default())
+            .await?;
+        let groups = df
+            .clone()
+            .select_columns(&["address"])?
+            .distinct()?
+            .count()
+            .await?;
+        let stats = vec![DescribeMethod::Count, DescribeMethod::Mean];
+        let by = vec!["address".to_string()];
+        let opts = DescribeOps::new(Some("person".to_string()), None, stats, vec![], by, None);
+        let dfd = DataFrameDescriber::try_new(df, &opts)?;
 
-        dfd.describe().await?;
+        let batches = dfd.describe().await?.collect().await?;
+        // count and mean, then the profile of `name`, for each address
+        assert_eq!(
+            batches.iter().map(|b| b.num_rows()).sum::<usize>(),
+            groups * 9
+        );
+        assert_eq!(batches[0].schema().field(0).name(), "address");
 
         Ok(())
     }
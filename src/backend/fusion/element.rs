@@ -0,0 +1,171 @@
+use std::{
+    any::Any,
+    sync::{Arc, LazyLock},
+};
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray},
+    compute::cast,
+    datatypes::{DataType, Field, Float64Type, UInt64Type},
+};
+use datafusion::{
+    common::ScalarValue,
+    error::Result,
+    logical_expr::{
+        function::{AccumulatorArgs, StateFieldsArgs},
+        Accumulator, AggregateUDF, AggregateUDFImpl, Expr, Signature, Volatility,
+    },
+};
+
+static ELEMENT_MIN: LazyLock<AggregateUDF> =
+    LazyLock::new(|| AggregateUDF::from(ElementStat::new(Stat::Min)));
+static ELEMENT_MAX: LazyLock<AggregateUDF> =
+    LazyLock::new(|| AggregateUDF::from(ElementStat::new(Stat::Max)));
+static ELEMENT_MEAN: LazyLock<AggregateUDF> =
+    LazyLock::new(|| AggregateUDF::from(ElementStat::new(Stat::Mean)));
+
+/// The smallest element of the numeric lists in `expr`.
+pub fn element_min(expr: Expr) -> Expr {
+    ELEMENT_MIN.call(vec![expr])
+}
+
+/// The largest element of the numeric lists in `expr`.
+pub fn element_max(expr: Expr) -> Expr {
+    ELEMENT_MAX.call(vec![expr])
+}
+
+/// The mean of all the elements of the numeric lists in `expr`.
+pub fn element_mean(expr: Expr) -> Expr {
+    ELEMENT_MEAN.call(vec![expr])
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Stat {
+    Min,
+    Max,
+    Mean,
+}
+
+#[derive(Debug)]
+struct ElementStat {
+    signature: Signature,
+    stat: Stat,
+}
+
+impl ElementStat {
+    fn new(stat: Stat) -> Self {
+        Self {
+            signature: Signature::any(1, Volatility::Immutable),
+            stat,
+        }
+    }
+}
+
+impl AggregateUDFImpl for ElementStat {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        match self.stat {
+            Stat::Min => "element_min",
+            Stat::Max => "element_max",
+            Stat::Mean => "element_mean",
+        }
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Float64)
+    }
+
+    fn accumulator(&self, _args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        Ok(Box::new(ElementAccumulator {
+            stat: self.stat,
+            min: None,
+            max: None,
+            sum: 0.0,
+            count: 0,
+        }))
+    }
+
+    fn state_fields(&self, _args: StateFieldsArgs) -> Result<Vec<Field>> {
+        Ok(vec![
+            Field::new("min", DataType::Float64, true),
+            Field::new("max", DataType::Float64, true),
+            Field::new("sum", DataType::Float64, false),
+            Field::new("count", DataType::UInt64, false),
+        ])
+    }
+}
+
+/// Keeps everything the three statistics need, the partial states are the same four values.
+#[derive(Debug)]
+struct ElementAccumulator {
+    stat: Stat,
+    min: Option<f64>,
+    max: Option<f64>,
+    sum: f64,
+    count: u64,
+}
+
+impl ElementAccumulator {
+    fn add(&mut self, min: f64, max: f64, sum: f64, count: u64) {
+        self.min = Some(self.min.map_or(min, |m| m.min(min)));
+        self.max = Some(self.max.map_or(max, |m| m.max(max)));
+        self.sum += sum;
+        self.count += count;
+    }
+}
+
+impl Accumulator for ElementAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        let item = Arc::new(Field::new_list_field(DataType::Float64, true));
+        let lists = cast(&values[0], &DataType::List(item))?;
+        for list in lists.as_list::<i32>().iter().flatten() {
+            for v in list.as_primitive::<Float64Type>().iter().flatten() {
+                self.add(v, v, v, 1);
+            }
+        }
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        let min = states[0].as_primitive::<Float64Type>();
+        let max = states[1].as_primitive::<Float64Type>();
+        let sum = states[2].as_primitive::<Float64Type>();
+        let count = states[3].as_primitive::<UInt64Type>();
+        for i in 0..min.len() {
+            // an accumulator that saw no element has no extrema
+            if !min.is_null(i) {
+                self.add(min.value(i), max.value(i), sum.value(i), count.value(i));
+            }
+        }
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        Ok(vec![
+            ScalarValue::Float64(self.min),
+            ScalarValue::Float64(self.max),
+            ScalarValue::Float64(Some(self.sum)),
+            ScalarValue::UInt64(Some(self.count)),
+        ])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        let value = match self.stat {
+            Stat::Min => self.min,
+            Stat::Max => self.max,
+            Stat::Mean => (self.count > 0).then(|| self.sum / self.count as f64),
+        };
+        Ok(ScalarValue::Float64(value))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+    }
+}
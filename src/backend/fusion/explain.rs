@@ -0,0 +1,108 @@
+use datafusion::{
+    execution::context::SQLOptions,
+    physical_plan::{display::DisplayableExecutionPlan, execute_stream, ExecutionPlan},
+    prelude::SessionContext,
+};
+use futures::StreamExt;
+use std::{fmt::Write, time::Instant};
+
+use crate::{cli::explain::ExplainOps, ErrorKind, ReplError};
+
+/// The metrics `explain --analyze` shows, summed over the partitions of each operator.
+/// Operators only report the ones that apply to them, e.g. `bytes_scanned` for Parquet scans.
+const METRICS: &[&str] = &[
+    "output_rows",
+    "elapsed_compute",
+    "spill_count",
+    "spilled_bytes",
+    "bytes_scanned",
+];
+
+pub(super) async fn explain(ctx: &SessionContext, opts: &ExplainOps) -> anyhow::Result<String> {
+    let state = ctx.state();
+    // `ctx.sql` would run a `create` or `drop` while planning it, explaining has no side effects
+    let logical = &state.create_logical_plan(&opts.query).await?;
+    SQLOptions::new()
+        .with_allow_ddl(false)
+        .with_allow_dml(false)
+        .verify_plan(logical)
+        .map_err(|e| {
+            ReplError::new(
+                ErrorKind::Planning,
+                e.to_string().replace("Error during planning: ", ""),
+            )
+            .with_hint("explain only plans queries, run the statement with `sql`")
+        })?;
+    let optimized = state.optimize(logical)?;
+    let physical = state
+        .query_planner()
+        .create_physical_plan(&optimized, &state)
+        .await?;
+
+    let mut out = String::new();
+    for (title, plan) in [
+        ("Logical plan", logical),
+        ("Optimized logical plan", &optimized),
+    ] {
+        let plan = match opts.verbose {
+            true => plan.display_indent_schema().to_string(),
+            false => plan.display_indent().to_string(),
+        };
+        writeln!(out, "{title}:")?;
+        for line in plan.lines() {
+            writeln!(out, "  {line}")?;
+        }
+    }
+
+    let summary = match opts.analyze {
+        true => {
+            let start = Instant::now();
+            // only the metrics are needed, so the result isn't kept
+            let mut stream = execute_stream(physical.clone(), ctx.task_ctx())?;
+            let mut rows = 0;
+            while let Some(batch) = stream.next().await {
+                rows += batch?.num_rows();
+            }
+            Some(format!("{rows} rows in {:.3?}", start.elapsed()))
+        }
+        false => None,
+    };
+    writeln!(out, "Physical plan:")?;
+    write_plan(&mut out, physical.as_ref(), 1, opts)?;
+    if let Some(summary) = summary {
+        writeln!(out, "{summary}")?;
+    }
+    Ok(out)
+}
+
+/// One operator per line, indented by its depth in the plan.
+fn write_plan(
+    out: &mut String,
+    plan: &dyn ExecutionPlan,
+    depth: usize,
+    opts: &ExplainOps,
+) -> std::fmt::Result {
+    let line = DisplayableExecutionPlan::new(plan)
+        .set_show_schema(opts.verbose)
+        .one_line()
+        .to_string();
+    write!(out, "{:indent$}{}", "", line.trim_end(), indent = depth * 2)?;
+
+    if let Some(metrics) = plan.metrics().filter(|_| opts.analyze) {
+        // `sum_by_name` skips the builtin metrics like `output_rows`, so match on the names
+        let values: Vec<_> = METRICS
+            .iter()
+            .filter_map(|name| {
+                let value = metrics.sum(|m| m.value().name() == *name)?;
+                Some(format!("{name}={value}"))
+            })
+            .collect();
+        write!(out, ", metrics=[{}]", values.join(", "))?;
+    }
+    writeln!(out)?;
+
+    for child in plan.children() {
+        write_plan(out, child.as_ref(), depth + 1, opts)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use anyhow::Context;
+use arrow::{
+    array::{Array, AsArray, RecordBatch},
+    compute::cast as cast_array,
+    datatypes::{DataType, Float64Type, Int64Type},
+};
+use datafusion::{
+    functions_aggregate::{
+        count::count,
+        min_max::{max, min},
+    },
+    logical_expr::{cast, col, lit, when, Expr},
+    prelude::SessionContext,
+};
+
+use crate::cli::hist::{HistOps, HistValues, Histogram};
+
+pub(super) async fn hist(ctx: &SessionContext, opts: &HistOps) -> anyhow::Result<Histogram> {
+    let df = ctx.sql(&format!("SELECT * FROM {}", opts.name)).await?;
+    let names: Vec<_> = df
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| f.name().as_str())
+        .collect();
+    opts.check_column(&names)?;
+    let data_type = df
+        .schema()
+        .field_with_unqualified_name(&opts.column)?
+        .data_type()
+        .clone();
+    let temporal = matches!(
+        data_type,
+        DataType::Date32 | DataType::Date64 | DataType::Timestamp(..)
+    );
+    if !temporal && !data_type.is_numeric() {
+        return Err(opts.unsupported(data_type).into());
+    }
+
+    let value = col(&opts.column);
+    let number = || cast(value.clone(), DataType::Float64);
+    // NaN sorts above every number, and the infinities are outside the range. Nulls aren't
+    // finite either
+    let finite = || number().between(lit(f64::MIN), lit(f64::MAX));
+    let finite_number = when(finite(), number()).end()?;
+    let stats = df
+        .clone()
+        .aggregate(
+            vec![],
+            vec![
+                count(lit(1)).alias("rows"),
+                count(value.clone()).alias("values"),
+                count(finite_number.clone()).alias("finite"),
+                min(finite_number.clone()).alias("min"),
+                max(finite_number).alias("max"),
+            ],
+        )?
+        .collect()
+        .await?;
+    let (rows, values) = (int(&stats, 0)?[0], int(&stats, 1)?[0]);
+    let nulls = (rows - values) as u64;
+    let non_finite = match temporal {
+        true => 0,
+        false => (values - int(&stats, 2)?[0]) as u64,
+    };
+    let df = match temporal {
+        true => df.filter(value.clone().is_not_null())?,
+        false => df.filter(finite())?,
+    };
+
+    if temporal {
+        let day = cast(cast(value, DataType::Date32), DataType::Int32);
+        let batches = df
+            .aggregate(vec![day.alias("day")], vec![count(lit(1)).alias("count")])?
+            .collect()
+            .await?;
+        let days = int(&batches, 0)?
+            .into_iter()
+            .zip(int(&batches, 1)?)
+            .map(|(day, count)| (day as i32, count as u64))
+            .collect();
+        return Ok(Histogram {
+            values: HistValues::Days(days),
+            nulls,
+            non_finite,
+        });
+    }
+
+    let (Some(min), Some(max)) = (float(&stats, 3)?, float(&stats, 4)?) else {
+        let values = HistValues::Bins {
+            min: 0.0,
+            max: 0.0,
+            counts: vec![],
+        };
+        return Ok(Histogram {
+            values,
+            nulls,
+            non_finite,
+        });
+    };
+    // a single value still gets a bin
+    let bins = if min == max { 1 } else { opts.bins() };
+    let width = (max - min) / bins as f64;
+    let last = lit(bins as i64 - 1);
+    let bin: Expr = cast((number() - lit(min)) / lit(width), DataType::Int64);
+    // the maximum falls right on the end of the last bin
+    let bin = when(bin.clone().gt_eq(last.clone()), last).otherwise(bin)?;
+    let batches = df
+        .aggregate(vec![bin.alias("bin")], vec![count(lit(1)).alias("count")])?
+        .collect()
+        .await?;
+    let mut counts = vec![0; bins];
+    for (bin, count) in int(&batches, 0)?.into_iter().zip(int(&batches, 1)?) {
+        counts[bin as usize] = count as u64;
+    }
+    Ok(Histogram {
+        values: HistValues::Bins { min, max, counts },
+        nulls,
+        non_finite,
+    })
+}
+
+/// The values of column `i` as integers. The columns read here have no nulls, one would shift
+/// the counts against their bins, so it's an error.
+fn int(batches: &[RecordBatch], i: usize) -> anyhow::Result<Vec<i64>> {
+    let mut values = vec![];
+    for batch in batches {
+        let array = cast_array(batch.column(i), &DataType::Int64)?;
+        for value in array.as_primitive::<Int64Type>() {
+            values.push(value.context("Unexpected null in the histogram counts")?);
+        }
+    }
+    Ok(values)
+}
+
+/// The first value of column `i`, as a float.
+fn float(batches: &[RecordBatch], i: usize) -> anyhow::Result<Option<f64>> {
+    let Some(batch) = batches.iter().find(|b| b.num_rows() > 0) else {
+        return Ok(None);
+    };
+    let array = cast_array(batch.column(i), &DataType::Float64)?;
+    let array = array.as_primitive::<Float64Type>();
+    Ok((!array.is_null(0)).then(|| array.value(0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use datafusion::prelude::CsvReadOptions;
+
+    #[tokio::test]
+    async fn hist_should_count_every_value_once() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_csv("person", "assets/person.csv", CsvReadOptions::default())
+            .await?;
+        let opts = HistOps::new("person".to_string(), "age".to_string(), Some(4), false);
+
+        let hist = hist(&ctx, &opts).await?;
+        let HistValues::Bins { counts, .. } = hist.values else {
+            panic!("age should have bins");
+        };
+        assert_eq!(counts.len(), 4);
+        let rows = ctx
+            .sql("SELECT count(age) FROM person")
+            .await?
+            .collect()
+            .await?;
+        assert_eq!(counts.iter().sum::<u64>() as i64, int(&rows, 0)?[0]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn hist_should_leave_out_nan_and_infinity() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.sql("CREATE VIEW t AS SELECT * FROM (VALUES (1.0), ('NaN'::double), (2.0), (NULL), ('inf'::double)) AS v(x)")
+            .await?;
+        let opts = HistOps::new("t".to_string(), "x".to_string(), Some(2), false);
+
+        let hist = hist(&ctx, &opts).await?;
+        assert_eq!((hist.nulls, hist.non_finite), (1, 2));
+        assert_eq!(
+            hist.values,
+            HistValues::Bins {
+                min: 1.0,
+                max: 2.0,
+                counts: vec![1, 1]
+            }
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use std::{fs::File, sync::Arc};
+
+use anyhow::Context;
+use arrow::{
+    array::RecordBatch,
+    buffer::Buffer,
+    datatypes::SchemaRef,
+    ipc::{
+        convert::fb_to_schema,
+        reader::{read_footer_length, FileDecoder},
+        root_as_footer, Block,
+    },
+};
+use bytes::Bytes;
+use memmap2::Mmap;
+
+/// Map an Arrow IPC file into memory and decode its batches in place: the arrays point into the
+/// mapping, only the buffers that aren't aligned get copied.
+pub(super) fn map_arrow_file(filename: &str) -> anyhow::Result<(SchemaRef, Vec<RecordBatch>)> {
+    let file = File::open(filename)?;
+    // SAFETY: the mapping is only read. Like any mapped file, it must not be truncated while
+    // it's connected.
+    let mmap = unsafe { Mmap::map(&file)? };
+    let buffer = Buffer::from(Bytes::from_owner(mmap));
+
+    // the file ends with the footer, its length and the `ARROW1` magic
+    let trailer = buffer
+        .len()
+        .checked_sub(10)
+        .with_context(|| format!("Not an Arrow IPC file: {filename}"))?;
+    let footer_len = read_footer_length(buffer[trailer..].try_into()?)?;
+    let footer_start = trailer
+        .checked_sub(footer_len)
+        .with_context(|| format!("Invalid Arrow IPC footer: {filename}"))?;
+    let footer = root_as_footer(&buffer[footer_start..trailer])
+        .map_err(|e| anyhow::anyhow!("Invalid Arrow IPC footer: {e}"))?;
+    let schema = footer
+        .schema()
+        .with_context(|| format!("Arrow IPC file without a schema: {filename}"))?;
+    let schema = Arc::new(fb_to_schema(schema));
+
+    // the blocks come from the file, a damaged one mustn't slice past the end of the mapping
+    let block_data = |block: &Block| {
+        let offset = usize::try_from(block.offset()).ok();
+        let len = usize::try_from(block.metaDataLength())
+            .ok()
+            .zip(usize::try_from(block.bodyLength()).ok())
+            .and_then(|(meta, body)| meta.checked_add(body));
+        offset
+            .zip(len)
+            .filter(|(offset, len)| {
+                offset
+                    .checked_add(*len)
+                    .is_some_and(|end| end <= buffer.len())
+            })
+            .map(|(offset, len)| buffer.slice_with_length(offset, len))
+            .with_context(|| {
+                format!("Truncated Arrow IPC file, a block is past its end: {filename}")
+            })
+    };
+    let mut decoder = FileDecoder::new(schema.clone(), footer.version());
+    for block in footer.dictionaries().iter().flatten() {
+        decoder.read_dictionary(block, &block_data(block)?)?;
+    }
+    let mut batches = Vec::new();
+    for block in footer.recordBatches().iter().flatten() {
+        batches.extend(decoder.read_record_batch(block, &block_data(block)?)?);
+    }
+    Ok((schema, batches))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use arrow::{
+        array::{Int32Array, StringArray},
+        datatypes::{DataType, Field, Schema},
+        ipc::writer::FileWriter,
+    };
+
+    fn write_batch(path: &std::path::Path) -> Result<(SchemaRef, RecordBatch)> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec![Some("a"), None])),
+            ],
+        )?;
+        let mut writer = FileWriter::try_new(File::create(path)?, &schema)?;
+        writer.write(&batch)?;
+        writer.write(&batch)?;
+        writer.finish()?;
+        Ok((schema, batch))
+    }
+
+    #[test]
+    fn map_arrow_file_should_read_every_batch() -> Result<()> {
+        let path = std::env::temp_dir().join("taotie_map_arrow_file_test.arrow");
+        let (schema, batch) = write_batch(&path)?;
+
+        let (mapped_schema, batches) = map_arrow_file(path.to_str().unwrap())?;
+        assert_eq!(mapped_schema, schema);
+        assert_eq!(batches, vec![batch.clone(), batch]);
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn map_arrow_file_should_fail_on_a_truncated_file() -> Result<()> {
+        let path = std::env::temp_dir().join("taotie_map_arrow_file_truncated_test.arrow");
+        write_batch(&path)?;
+        // cut the batches short, but keep the footer listing them
+        let bytes = std::fs::read(&path)?;
+        let footer_len = read_footer_length(bytes[bytes.len() - 10..].try_into()?)? + 10;
+        let truncated = [&bytes[..64], &bytes[bytes.len() - footer_len..]].concat();
+        std::fs::write(&path, truncated)?;
+
+        let err = map_arrow_file(path.to_str().unwrap()).unwrap_err();
+        assert!(
+            err.to_string().contains("Truncated Arrow IPC file"),
+            "{err}"
+        );
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
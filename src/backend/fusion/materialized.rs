@@ -0,0 +1,54 @@
+use std::{any::Any, sync::Arc};
+
+use arrow::{array::RecordBatch, datatypes::SchemaRef};
+use async_trait::async_trait;
+use datafusion::{
+    catalog::Session,
+    datasource::{MemTable, TableProvider, TableType},
+    error::Result,
+    logical_expr::{Expr, TableProviderFilterPushDown},
+    physical_plan::ExecutionPlan,
+};
+
+/// An in-memory table holding a query result. It's a `MemTable` that reports itself as a
+/// temporary table, so `list` can tell it apart from file-backed tables.
+#[derive(Debug)]
+pub struct MaterializedTable(MemTable);
+
+impl MaterializedTable {
+    pub fn try_new(schema: SchemaRef, batches: Vec<RecordBatch>) -> anyhow::Result<Self> {
+        Ok(Self(MemTable::try_new(schema, vec![batches])?))
+    }
+}
+
+#[async_trait]
+impl TableProvider for MaterializedTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.0.schema()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Temporary
+    }
+
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        self.0.scan(state, projection, filters, limit).await
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> Result<Vec<TableProviderFilterPushDown>> {
+        self.0.supports_filters_pushdown(filters)
+    }
+}
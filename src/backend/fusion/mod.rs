@@ -1,20 +1,80 @@
+mod corr;
+mod crosstab;
 mod describe;
+mod element;
+mod explain;
+mod hist;
+mod ipc;
+mod materialized;
+mod mode;
+mod postgres;
+mod sheet;
+mod source;
+mod top;
 
-use crate::{cli::connect::DatasetConn, Backend, ReplDisplay};
+pub use describe::DataFrameDescriber;
+
+use crate::{
+    catalog::SessionCatalog,
+    cli::{
+        connect::{is_arrow_stream, DatasetConn, JsonOps},
+        corr::CorrOps,
+        create::CreateOps,
+        crosstab::CrosstabOps,
+        describe::DescribeOps,
+        explain::ExplainOps,
+        export::{ExportFormat, ExportOps},
+        hist::{HistOps, Histogram},
+        top::TopOps,
+    },
+    document::{is_document, read_records},
+    format::{format_batches, OutputFormat},
+    pager::Pending,
+    settings::Settings,
+    workbook::{read_sheet, unselected_sheets},
+    Backend, ErrorKind, ReplDisplay, ReplError, ReplRows,
+};
+use arrow::{
+    array::{AsArray, RecordBatch, RecordBatchReader, RecordBatchWriter, StringArray},
+    compute::concat_batches,
+    datatypes::{DataType, Field, Schema, UInt64Type},
+    ipc::{
+        reader::StreamReader,
+        writer::{FileWriter, StreamWriter},
+    },
+    json::{self, reader::infer_json_schema_from_iterator},
+};
 use datafusion::{
-    arrow::util::pretty::pretty_format_batches,
+    common::config::{CsvOptions, JsonOptions, TableParquetOptions},
+    dataframe::DataFrameWriteOptions,
+    datasource::MemTable,
+    execution::SendableRecordBatchStream,
     prelude::{CsvReadOptions, DataFrame, NdJsonReadOptions, SessionConfig, SessionContext},
 };
-use describe::DataFrameDescriber;
-use std::ops::Deref;
+use futures::StreamExt;
+use materialized::MaterializedTable;
+use postgres::PostgresTable;
+use std::{collections::BTreeMap, fs::File, io::BufReader, ops::Deref, sync::Arc};
 
-pub struct DataFusionBackend(SessionContext);
+pub struct DataFusionBackend {
+    ctx: SessionContext,
+    catalog: SessionCatalog,
+    settings: Settings,
+    pending: Option<Pending<Self>>,
+    last_result: Option<DataFrame>,
+}
 
 impl DataFusionBackend {
-    pub fn new() -> Self {
+    pub fn new(catalog: SessionCatalog) -> Self {
         let mut config = SessionConfig::new();
         config.options_mut().catalog.information_schema = true;
-        Self(SessionContext::new_with_config(config))
+        Self {
+            ctx: SessionContext::new_with_config(config),
+            catalog,
+            settings: Settings::default(),
+            pending: None,
+            last_result: None,
+        }
     }
 }
 
@@ -23,12 +83,15 @@ impl Backend for DataFusionBackend {
 
     async fn connect(&mut self, opts: &crate::cli::connect::ConnectOps) -> anyhow::Result<()> {
         match &opts.conn {
-            DatasetConn::Postgres(_conn_str) => {
-                println!("Connected to Postgres");
+            DatasetConn::Postgres(conn_str) => {
+                let table = opts.table.as_deref().unwrap_or(&opts.name);
+                let provider = PostgresTable::try_new(conn_str, table).await?;
+                self.register_table(&opts.name, Arc::new(provider))?;
             }
             DatasetConn::CSv(file_opts) => {
+                let file_extension = file_opts.file_extension();
                 let csv_options = CsvReadOptions {
-                    file_extension: &file_opts.ext,
+                    file_extension: &file_extension,
                     file_compression_type: file_opts.compression,
                     ..Default::default()
                 };
@@ -39,69 +102,369 @@ impl Backend for DataFusionBackend {
                 self.register_parquet(&opts.name, &filename, Default::default())
                     .await?;
             }
-            DatasetConn::NdJson(file_opts) => {
+            DatasetConn::Json(json_opts) if is_document(json_opts)? => {
+                // inferred like NDJSON, from all the records
+                let records = read_records(json_opts)?;
+                let schema = Arc::new(infer_json_schema_from_iterator(records.iter().map(Ok))?);
+                let mut decoder = json::ReaderBuilder::new(schema.clone()).build_decoder()?;
+                decoder.serialize(&records)?;
+                let batches = decoder.flush()?.into_iter().collect();
+                let table = MemTable::try_new(schema, vec![batches])?;
+                self.register_table(&opts.name, Arc::new(table))?;
+            }
+            DatasetConn::NdJson(file_opts)
+            | DatasetConn::Json(JsonOps {
+                file: file_opts, ..
+            }) => {
+                let file_extension = file_opts.file_extension();
                 let json_options = NdJsonReadOptions {
-                    file_extension: &file_opts.ext,
+                    file_extension: &file_extension,
                     file_compression_type: file_opts.compression,
                     ..Default::default()
                 };
                 self.register_json(&opts.name, &file_opts.filename, json_options)
                     .await?;
             }
+            DatasetConn::Avro(filename) => {
+                self.register_avro(&opts.name, filename, Default::default())
+                    .await?;
+            }
+            DatasetConn::Orc(filename) => {
+                // there's no ORC table provider, the file is read once into memory
+                let reader = orc_rust::ArrowReaderBuilder::try_new(File::open(filename)?)?.build();
+                let schema = reader.schema();
+                let batches = reader.collect::<Result<Vec<_>, _>>()?;
+                let table = MemTable::try_new(schema, vec![batches])?;
+                self.register_table(&opts.name, Arc::new(table))?;
+            }
+            DatasetConn::Excel(sheet_opts) => {
+                let (table, sheets) = read_sheet(sheet_opts)?;
+                let batch = sheet::sheet_batch(table)?;
+                let table = MemTable::try_new(batch.schema(), vec![vec![batch]])?;
+                self.register_table(&opts.name, Arc::new(table))?;
+                self.catalog.set_sheets(&opts.name, sheets);
+            }
+            DatasetConn::Arrow(filename) => {
+                let (schema, batches) = match is_arrow_stream(filename)? {
+                    true => {
+                        let file = BufReader::new(File::open(filename)?);
+                        let reader = StreamReader::try_new(file, None)?;
+                        (reader.schema(), reader.collect::<Result<_, _>>()?)
+                    }
+                    false => ipc::map_arrow_file(filename)?,
+                };
+                let table = MemTable::try_new(schema, vec![batches])?;
+                self.register_table(&opts.name, Arc::new(table))?;
+            }
         };
 
         Ok(())
     }
 
     async fn list(&self) -> anyhow::Result<Self::DataFrame> {
-        let sql = "select table_name, table_type from information_schema.tables where table_schema = 'public'";
-        let df = self.0.sql(sql).await?;
+        let sql = "select table_name, \
+            case table_type when 'VIEW' then 'view' when 'LOCAL TEMPORARY' then 'materialized' else 'table' end as table_type \
+            from information_schema.tables where table_schema = 'public'";
+        let df = self.ctx.sql(sql).await?;
         Ok(df)
     }
 
+    async fn sheets(&self) -> anyhow::Result<Option<Self::DataFrame>> {
+        match unselected_sheets(&self.catalog) {
+            Some(table) => Ok(Some(
+                SessionContext::new().read_batch(sheet::sheet_batch(table)?)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    async fn columns(&self) -> anyhow::Result<BTreeMap<String, Vec<String>>> {
+        let sql = "select table_name, column_name from information_schema.columns \
+            where table_schema = 'public' order by table_name, ordinal_position";
+        let mut ret: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for batch in self.ctx.sql(sql).await?.collect().await? {
+            let tables = batch.column(0).as_string::<i32>();
+            let columns = batch.column(1).as_string::<i32>();
+            for (table, column) in tables.iter().zip(columns).flat_map(|(t, c)| t.zip(c)) {
+                ret.entry(table.to_string())
+                    .or_default()
+                    .push(column.to_string());
+            }
+        }
+        Ok(ret)
+    }
+
     async fn schema(&self, name: &str) -> anyhow::Result<Self::DataFrame> {
-        let df = self.0.sql(&format!("DESCRIBE {name}")).await?;
-        Ok(df)
+        let df = self.ctx.sql(&format!("DESCRIBE {name}")).await?;
+        let Some(types) = self.catalog.dataset(name).map(source::source_types) else {
+            return Ok(df);
+        };
+        let Some(types) = types? else {
+            return Ok(df);
+        };
+
+        // the types declared in the file go next to their Arrow types
+        let schema = Arc::new(df.schema().as_arrow().clone());
+        let batch = concat_batches(&schema, &df.collect().await?)?;
+        let source_types: StringArray = batch
+            .column(0)
+            .as_string::<i32>()
+            .iter()
+            .map(|c| c.and_then(|c| types.get(c)))
+            .collect();
+        let mut columns = batch.columns().to_vec();
+        columns.insert(2, Arc::new(source_types));
+        let mut fields = schema.fields().to_vec();
+        fields.insert(2, Arc::new(Field::new("source_type", DataType::Utf8, true)));
+        let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)?;
+        Ok(SessionContext::new().read_batch(batch)?)
     }
 
-    async fn describe(&self, name: &str) -> anyhow::Result<Self::DataFrame> {
-        let df = self.0.sql(&format!("select * from {name}")).await?;
-        let ddf = DataFrameDescriber::try_new(df)?;
+    async fn layout(&self, name: &str) -> anyhow::Result<Option<Self::DataFrame>> {
+        match self.catalog.dataset(name) {
+            Some(DatasetConn::Orc(filename)) => {
+                let stripes = source::orc_stripes(filename)?;
+                Ok(Some(SessionContext::new().read_batch(stripes)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn describe(
+        &self,
+        df: Self::DataFrame,
+        opts: &DescribeOps,
+    ) -> anyhow::Result<Self::DataFrame> {
+        let ddf = DataFrameDescriber::try_new(df, opts)?;
         ddf.describe().await
     }
 
     async fn head(&self, name: &str, size: usize) -> anyhow::Result<Self::DataFrame> {
         let df = self
-            .0
+            .ctx
             .sql(&format!("SELECT * FROM {name} LIMIT {size}"))
             .await?;
         Ok(df)
     }
 
+    async fn top(&self, opts: &TopOps) -> anyhow::Result<Self::DataFrame> {
+        top::top(&self.ctx, opts).await
+    }
+
+    async fn hist(&self, opts: &HistOps) -> anyhow::Result<Histogram> {
+        hist::hist(&self.ctx, opts).await
+    }
+
+    async fn corr(&self, opts: &CorrOps) -> anyhow::Result<Self::DataFrame> {
+        corr::corr(&self.ctx, opts).await
+    }
+
+    async fn crosstab(&self, opts: &CrosstabOps) -> anyhow::Result<Self::DataFrame> {
+        crosstab::crosstab(&self.ctx, opts).await
+    }
+
     async fn sql(&self, sql: &str) -> anyhow::Result<Self::DataFrame> {
-        let df = self.0.sql(sql).await?;
+        let df = self.ctx.sql(sql).await?;
         Ok(df)
     }
+
+    async fn explain(&self, opts: &ExplainOps) -> anyhow::Result<String> {
+        explain::explain(&self.ctx, opts).await
+    }
+
+    async fn export(&self, opts: &ExportOps) -> anyhow::Result<usize> {
+        let df = self.ctx.sql(&opts.query()).await?;
+        let target = &opts.target;
+        let write_opts = DataFrameWriteOptions::new().with_single_file_output(true);
+
+        let batches = match target.format {
+            ExportFormat::Csv => {
+                let csv_options = CsvOptions {
+                    has_header: Some(true),
+                    compression: target.compression.into(),
+                    ..Default::default()
+                };
+                df.write_csv(&target.filename, write_opts, Some(csv_options))
+                    .await?
+            }
+            ExportFormat::NdJson => {
+                let json_options = JsonOptions {
+                    compression: target.compression.into(),
+                    ..Default::default()
+                };
+                df.write_json(&target.filename, write_opts, Some(json_options))
+                    .await?
+            }
+            ExportFormat::Parquet => {
+                let mut parquet_options = TableParquetOptions::default();
+                if let Some(compression) = &opts.compression {
+                    parquet_options.global.compression = Some(compression.clone());
+                }
+                if let Some(size) = opts.row_group_size {
+                    parquet_options.global.max_row_group_size = size;
+                }
+                df.write_parquet(&target.filename, write_opts, Some(parquet_options))
+                    .await?
+            }
+            ExportFormat::Arrow => {
+                let schema = df.schema().as_arrow().clone();
+                let writer = FileWriter::try_new(File::create(&target.filename)?, &schema)?;
+                return write_batches(df.execute_stream().await?, writer).await;
+            }
+            ExportFormat::ArrowStream => {
+                let schema = df.schema().as_arrow().clone();
+                let writer = StreamWriter::try_new(File::create(&target.filename)?, &schema)?;
+                return write_batches(df.execute_stream().await?, writer).await;
+            }
+        };
+
+        // the file writers return a single row with the number of rows written
+        let rows = batches
+            .first()
+            .and_then(|b| b.column(0).as_primitive_opt::<UInt64Type>())
+            .map(|counts| counts.value(0) as usize)
+            .unwrap_or_default();
+        Ok(rows)
+    }
+
+    async fn create(&mut self, opts: &CreateOps) -> anyhow::Result<()> {
+        let df = self.ctx.sql(&opts.sql()).await?;
+        if opts.materialize {
+            let schema = df.schema().inner().clone();
+            let table = MaterializedTable::try_new(schema, df.collect().await?)?;
+            self.register_table(&opts.name, Arc::new(table))?;
+        } else {
+            self.register_table(&opts.name, df.into_view())?;
+        }
+        Ok(())
+    }
+
+    async fn drop(&mut self, name: &str) -> anyhow::Result<()> {
+        match self.deregister_table(name)? {
+            Some(_) => Ok(()),
+            None => Err(
+                ReplError::new(ErrorKind::Planning, format!("Dataset not found: {name}")).into(),
+            ),
+        }
+    }
+
+    fn catalog(&mut self) -> &mut SessionCatalog {
+        &mut self.catalog
+    }
+
+    fn settings(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    fn pending(&mut self) -> &mut Option<Pending<Self>> {
+        &mut self.pending
+    }
+
+    fn last_result(&mut self) -> &mut Option<Self::DataFrame> {
+        &mut self.last_result
+    }
 }
 
 impl Default for DataFusionBackend {
     fn default() -> Self {
-        Self::new()
+        Self::new(SessionCatalog::default())
+    }
+}
+
+/// Write every batch of `stream` as it comes, and the number of rows written.
+async fn write_batches(
+    mut stream: SendableRecordBatchStream,
+    mut writer: impl RecordBatchWriter,
+) -> anyhow::Result<usize> {
+    let mut rows = 0;
+    while let Some(batch) = stream.next().await {
+        let batch = batch?;
+        rows += batch.num_rows();
+        writer.write(&batch)?;
     }
+    writer.close()?;
+    Ok(rows)
 }
 
 impl Deref for DataFusionBackend {
     type Target = SessionContext;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.ctx
     }
 }
 
 impl ReplDisplay for DataFrame {
-    async fn display(self) -> anyhow::Result<String> {
-        let batches = self.collect().await?;
-        let data = pretty_format_batches(&batches)?;
-        Ok(data.to_string())
+    type Rows = BatchRows;
+
+    async fn rows(self) -> anyhow::Result<Self::Rows> {
+        Ok(BatchRows {
+            stream: self.execute_stream().await?,
+            buffered: None,
+        })
+    }
+}
+
+/// A result streamed a batch at a time. The part of a batch that didn't fit in a page is kept
+/// for the next one.
+pub struct BatchRows {
+    stream: SendableRecordBatchStream,
+    buffered: Option<RecordBatch>,
+}
+
+impl BatchRows {
+    async fn next_batch(&mut self) -> anyhow::Result<Option<RecordBatch>> {
+        if let Some(batch) = self.buffered.take() {
+            return Ok(Some(batch));
+        }
+        while let Some(batch) = self.stream.next().await {
+            let batch = batch?;
+            if batch.num_rows() > 0 {
+                return Ok(Some(batch));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl ReplRows for BatchRows {
+    async fn next_page(
+        &mut self,
+        format: OutputFormat,
+        max_rows: usize,
+    ) -> anyhow::Result<(String, bool)> {
+        let mut batches = Vec::new();
+        let mut rows = 0;
+        while max_rows == 0 || rows < max_rows {
+            let Some(batch) = self.next_batch().await? else {
+                break;
+            };
+            let n = match max_rows {
+                0 => batch.num_rows(),
+                _ => batch.num_rows().min(max_rows - rows),
+            };
+            if n < batch.num_rows() {
+                self.buffered = Some(batch.slice(n, batch.num_rows() - n));
+            }
+            rows += n;
+            batches.push(batch.slice(0, n));
+        }
+        if batches.is_empty() {
+            // still show the column names of an empty result
+            batches.push(RecordBatch::new_empty(self.stream.schema()));
+        }
+
+        // peek, so the caller knows whether there's another page
+        self.buffered = self.next_batch().await?;
+        let ret = format_batches(&batches, format)?;
+        Ok((ret, self.buffered.is_some()))
+    }
+
+    async fn count_rest(mut self) -> anyhow::Result<usize> {
+        let mut count = 0;
+        while let Some(batch) = self.next_batch().await? {
+            count += batch.num_rows();
+        }
+        Ok(count)
     }
 }
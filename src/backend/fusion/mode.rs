@@ -0,0 +1,169 @@
+use std::{any::Any, cmp::Ordering, collections::HashMap, sync::LazyLock};
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray},
+    datatypes::{DataType, Field, Int64Type},
+};
+use datafusion::{
+    common::ScalarValue,
+    error::Result,
+    logical_expr::{
+        function::{AccumulatorArgs, StateFieldsArgs},
+        Accumulator, AggregateUDF, AggregateUDFImpl, Expr, Signature, Volatility,
+    },
+};
+
+use crate::cli::describe::TOP_VALUES;
+
+static MODE: LazyLock<AggregateUDF> = LazyLock::new(|| AggregateUDF::from(Mode::new(None)));
+static TOP: LazyLock<AggregateUDF> =
+    LazyLock::new(|| AggregateUDF::from(Mode::new(Some(TOP_VALUES))));
+
+/// The most frequent non-null value of `expr`, the smallest one on a tie.
+pub fn mode(expr: Expr) -> Expr {
+    MODE.call(vec![expr])
+}
+
+/// The `TOP_VALUES` most frequent non-null values of `expr` with their counts, as text, e.g.
+/// `a (3), b (2)`. Ties are ordered like `mode`.
+pub fn top_values(expr: Expr) -> Expr {
+    TOP.call(vec![expr])
+}
+
+/// `mode`, or the top values when `top` is set.
+#[derive(Debug)]
+struct Mode {
+    signature: Signature,
+    top: Option<usize>,
+}
+
+impl Mode {
+    fn new(top: Option<usize>) -> Self {
+        Self {
+            signature: Signature::any(1, Volatility::Immutable),
+            top,
+        }
+    }
+}
+
+impl AggregateUDFImpl for Mode {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        match self.top {
+            Some(_) => "top_values",
+            None => "mode",
+        }
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        match self.top {
+            Some(_) => Ok(DataType::Utf8),
+            None => Ok(arg_types[0].clone()),
+        }
+    }
+
+    fn accumulator(&self, args: AccumulatorArgs) -> Result<Box<dyn Accumulator>> {
+        Ok(Box::new(ModeAccumulator {
+            data_type: args.exprs[0].data_type(args.schema)?,
+            top: self.top,
+            counts: HashMap::new(),
+        }))
+    }
+
+    fn state_fields(&self, args: StateFieldsArgs) -> Result<Vec<Field>> {
+        let values = Field::new_list_field(args.input_types[0].clone(), true);
+        let counts = Field::new_list_field(DataType::Int64, true);
+        Ok(vec![
+            Field::new("values", DataType::List(values.into()), true),
+            Field::new("counts", DataType::List(counts.into()), true),
+        ])
+    }
+}
+
+/// Counts every distinct value. The partial states are the values and their counts.
+#[derive(Debug)]
+struct ModeAccumulator {
+    data_type: DataType,
+    top: Option<usize>,
+    counts: HashMap<ScalarValue, i64>,
+}
+
+impl ModeAccumulator {
+    // the `avro` feature puts an Avro error in `DataFusionError`, which makes it this big
+    #[allow(clippy::result_large_err)]
+    fn add(&mut self, values: &ArrayRef, counts: Option<&[i64]>) -> Result<()> {
+        for i in 0..values.len() {
+            if values.is_null(i) {
+                continue;
+            }
+            let value = ScalarValue::try_from_array(values, i)?;
+            *self.counts.entry(value).or_default() += counts.map_or(1, |c| c[i]);
+        }
+        Ok(())
+    }
+}
+
+impl Accumulator for ModeAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.add(&values[0], None)
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        let (values, counts) = (states[0].as_list::<i32>(), states[1].as_list::<i32>());
+        for (values, counts) in values.iter().zip(counts.iter()) {
+            if let (Some(values), Some(counts)) = (values, counts) {
+                self.add(&values, Some(counts.as_primitive::<Int64Type>().values()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        let (values, counts): (Vec<_>, Vec<_>) = self
+            .counts
+            .iter()
+            .map(|(v, c)| (v.clone(), ScalarValue::Int64(Some(*c))))
+            .unzip();
+        Ok(vec![
+            ScalarValue::List(ScalarValue::new_list_nullable(&values, &self.data_type)),
+            ScalarValue::List(ScalarValue::new_list_nullable(&counts, &DataType::Int64)),
+        ])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        let mut counts: Vec<_> = self.counts.iter().collect();
+        counts.sort_by(|(v1, c1), (v2, c2)| {
+            c2.cmp(c1)
+                .then(v1.partial_cmp(v2).unwrap_or(Ordering::Equal))
+        });
+        match (self.top, counts.first()) {
+            (Some(_), None) => Ok(ScalarValue::Utf8(None)),
+            (Some(k), Some(_)) => {
+                let top: Vec<_> = counts
+                    .iter()
+                    .take(k)
+                    .map(|(v, c)| format!("{v} ({c})"))
+                    .collect();
+                Ok(ScalarValue::Utf8(Some(top.join(", "))))
+            }
+            (None, Some((v, _))) => Ok((*v).clone()),
+            (None, None) => ScalarValue::try_from(&self.data_type),
+        }
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+            + self
+                .counts
+                .keys()
+                .map(|v| v.size() + std::mem::size_of::<i64>())
+                .sum::<usize>()
+    }
+}
@@ -0,0 +1,510 @@
+use std::{any::Any, fmt, sync::Arc};
+
+use arrow::{
+    array::{
+        ArrayRef, BinaryBuilder, BooleanBuilder, Date32Builder, Float32Builder, Float64Builder,
+        Int16Builder, Int32Builder, Int64Builder, RecordBatch, RecordBatchOptions, StringBuilder,
+        TimestampMicrosecondBuilder,
+    },
+    datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use datafusion::{
+    catalog::Session,
+    common::tree_node::{Transformed, TreeNode, TreeNodeRecursion},
+    datasource::{TableProvider, TableType},
+    error::{DataFusionError, Result},
+    execution::{SendableRecordBatchStream, TaskContext},
+    logical_expr::{Expr, Operator, TableProviderFilterPushDown},
+    physical_expr::EquivalenceProperties,
+    physical_plan::{
+        execution_plan::{Boundedness, EmissionType},
+        stream::RecordBatchStreamAdapter,
+        DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, PlanProperties,
+    },
+    sql::unparser::{dialect::PostgreSqlDialect, Unparser},
+};
+use futures::{stream, StreamExt, TryStreamExt};
+use tokio_postgres::{
+    types::{ToSql, Type},
+    Client, Config, NoTls, Row,
+};
+
+/// A DataFusion table backed by a single Postgres table. Projections, filters and limits are
+/// rendered back to SQL and evaluated by Postgres.
+#[derive(Debug)]
+pub struct PostgresTable {
+    client: Arc<Client>,
+    table: String,
+    columns: Vec<PgColumn>,
+    schema: SchemaRef,
+}
+
+#[derive(Debug, Clone)]
+struct PgColumn {
+    name: String,
+    kind: PgKind,
+}
+
+/// How a Postgres column is read into Arrow. Types we can't decode natively are cast to
+/// text (or float8 for numeric) on the server side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PgKind {
+    Bool,
+    Int2,
+    Int4,
+    Int8,
+    Float4,
+    Float8,
+    Text,
+    Bytea,
+    Date,
+    Timestamp,
+    TimestampTz,
+    Numeric,
+    Other,
+}
+
+#[derive(Debug)]
+struct PostgresExec {
+    client: Arc<Client>,
+    sql: String,
+    columns: Vec<PgColumn>,
+    schema: SchemaRef,
+    properties: PlanProperties,
+}
+
+impl PostgresTable {
+    pub async fn try_new(conn_str: &str, table: &str) -> anyhow::Result<Self> {
+        let mut config: Config = conn_str.parse()?;
+        // the session file keeps no passwords, see `DatasetConn::without_password`
+        if config.get_password().is_none() {
+            if let Ok(password) = std::env::var("PGPASSWORD") {
+                config.password(password);
+            }
+        }
+        let (client, connection) = config.connect(NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                eprintln!("Postgres connection error: {}", e);
+            }
+        });
+
+        let table = quote_table(table);
+        let stmt = client
+            .prepare(&format!("SELECT * FROM {table} LIMIT 0"))
+            .await?;
+        let columns: Vec<_> = stmt
+            .columns()
+            .iter()
+            .map(|c| PgColumn {
+                name: c.name().to_string(),
+                kind: PgKind::from(c.type_()),
+            })
+            .collect();
+        let fields: Vec<_> = columns
+            .iter()
+            .map(|c| Field::new(&c.name, c.kind.data_type(), true))
+            .collect();
+
+        Ok(Self {
+            client: Arc::new(client),
+            table,
+            columns,
+            schema: Arc::new(Schema::new(fields)),
+        })
+    }
+
+    fn build_sql(&self, projection: &[usize], filters: &[Expr], limit: Option<usize>) -> String {
+        let cols = projection
+            .iter()
+            .map(|i| self.columns[*i].select_expr())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut sql = format!("SELECT {cols} FROM {}", self.table);
+
+        // the other filters are left to DataFusion
+        let conditions: Vec<_> = filters.iter().filter_map(|f| self.condition(f)).collect();
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        if let Some(limit) = limit {
+            sql.push_str(&format!(" LIMIT {limit}"));
+        }
+        sql
+    }
+
+    /// The Postgres condition of a filter, if Postgres evaluates it exactly like DataFusion.
+    fn condition(&self, filter: &Expr) -> Option<String> {
+        is_exact(&self.columns, filter)
+            .then(|| filter_to_sql(filter))
+            .flatten()
+    }
+}
+
+/// Whether Postgres evaluates `filter` exactly like DataFusion would: it only compares natively
+/// decoded columns to plain literals, with operators that mean the same in both. Text is only
+/// compared for equality, since Postgres orders it by its collation.
+// `apply` takes a closure returning a `DataFusionError`, big with the `avro` feature
+#[allow(clippy::result_large_err)]
+fn is_exact(columns: &[PgColumn], filter: &Expr) -> bool {
+    let kind = |name: &str| columns.iter().find(|c| c.name == name).map(|c| c.kind);
+    let has_text = |e: &Expr| {
+        e.column_refs()
+            .iter()
+            .any(|c| kind(&c.name) == Some(PgKind::Text))
+    };
+    let mut exact = true;
+    let _ = filter.apply(|e| {
+        exact = match e {
+            Expr::Column(c) => kind(&c.name).is_some_and(|k| k.is_native()),
+            Expr::Literal(v) => matches!(
+                v.data_type(),
+                DataType::Null
+                    | DataType::Boolean
+                    | DataType::Int8
+                    | DataType::Int16
+                    | DataType::Int32
+                    | DataType::Int64
+                    | DataType::Float32
+                    | DataType::Float64
+                    | DataType::Utf8
+                    | DataType::Date32
+            ),
+            Expr::BinaryExpr(b) => match b.op {
+                Operator::Eq | Operator::NotEq | Operator::And | Operator::Or => true,
+                Operator::Lt | Operator::LtEq | Operator::Gt | Operator::GtEq => {
+                    !has_text(&b.left) && !has_text(&b.right)
+                }
+                _ => false,
+            },
+            Expr::Between(b) => !has_text(&b.expr),
+            Expr::Not(_) | Expr::IsNull(_) | Expr::IsNotNull(_) | Expr::InList(_) => true,
+            Expr::Like(l) => l.escape_char.is_none(),
+            _ => false,
+        };
+        Ok(match exact {
+            true => TreeNodeRecursion::Continue,
+            false => TreeNodeRecursion::Stop,
+        })
+    });
+    exact && filter_to_sql(filter).is_some()
+}
+
+#[async_trait]
+impl TableProvider for PostgresTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    async fn scan(
+        &self,
+        _state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let projection = match projection {
+            Some(p) => p.clone(),
+            None => (0..self.columns.len()).collect(),
+        };
+        let sql = self.build_sql(&projection, filters, limit);
+        let columns: Vec<_> = projection
+            .iter()
+            .map(|i| self.columns[*i].clone())
+            .collect();
+        let schema = Arc::new(self.schema.project(&projection)?);
+
+        Ok(Arc::new(PostgresExec::new(
+            self.client.clone(),
+            sql,
+            columns,
+            schema,
+        )))
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> Result<Vec<TableProviderFilterPushDown>> {
+        // a filter is either sent to Postgres whole or not at all, then DataFusion applies it
+        Ok(filters
+            .iter()
+            .map(|f| match self.condition(f) {
+                Some(_) => TableProviderFilterPushDown::Exact,
+                None => TableProviderFilterPushDown::Unsupported,
+            })
+            .collect())
+    }
+}
+
+impl PostgresExec {
+    fn new(client: Arc<Client>, sql: String, columns: Vec<PgColumn>, schema: SchemaRef) -> Self {
+        let properties = PlanProperties::new(
+            EquivalenceProperties::new(schema.clone()),
+            Partitioning::UnknownPartitioning(1),
+            EmissionType::Incremental,
+            Boundedness::Bounded,
+        );
+        Self {
+            client,
+            sql,
+            columns,
+            schema,
+            properties,
+        }
+    }
+}
+
+impl DisplayAs for PostgresExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PostgresExec: sql={}", self.sql)
+    }
+}
+
+impl ExecutionPlan for PostgresExec {
+    fn name(&self) -> &str {
+        "PostgresExec"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.properties
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        _children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Ok(self)
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn execute(
+        &self,
+        _partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        let client = self.client.clone();
+        let sql = self.sql.clone();
+        let columns = self.columns.clone();
+        let schema = self.schema.clone();
+        let batch_size = context.session_config().batch_size();
+        let external = |e: tokio_postgres::Error| DataFusionError::External(Box::new(e));
+
+        // rows are decoded as they arrive, so a consumer that stops early doesn't read them all
+        let rows = stream::once(async move {
+            let params: [&(dyn ToSql + Sync); 0] = [];
+            client.query_raw(&sql, params).await.map_err(external)
+        })
+        .map_ok(move |rows| rows.map_err(external))
+        .try_flatten();
+        let batches = {
+            let schema = schema.clone();
+            rows.try_chunks(batch_size)
+                .map(move |rows| rows_to_batch(&rows.map_err(|e| e.1)?, &columns, schema.clone()))
+        };
+
+        Ok(Box::pin(RecordBatchStreamAdapter::new(schema, batches)))
+    }
+}
+
+impl PgColumn {
+    fn select_expr(&self) -> String {
+        let name = quote_ident(&self.name);
+        match self.kind {
+            PgKind::Numeric => format!("{name}::float8 AS {name}"),
+            PgKind::Other => format!("{name}::text AS {name}"),
+            _ => name,
+        }
+    }
+}
+
+impl PgKind {
+    fn data_type(&self) -> DataType {
+        match self {
+            PgKind::Bool => DataType::Boolean,
+            PgKind::Int2 => DataType::Int16,
+            PgKind::Int4 => DataType::Int32,
+            PgKind::Int8 => DataType::Int64,
+            PgKind::Float4 => DataType::Float32,
+            PgKind::Float8 | PgKind::Numeric => DataType::Float64,
+            PgKind::Text | PgKind::Other => DataType::Utf8,
+            PgKind::Bytea => DataType::Binary,
+            PgKind::Date => DataType::Date32,
+            PgKind::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, None),
+            PgKind::TimestampTz => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        }
+    }
+
+    fn is_native(&self) -> bool {
+        !matches!(self, PgKind::Numeric | PgKind::Other)
+    }
+}
+
+impl From<&Type> for PgKind {
+    fn from(ty: &Type) -> Self {
+        match *ty {
+            Type::BOOL => PgKind::Bool,
+            Type::INT2 => PgKind::Int2,
+            Type::INT4 => PgKind::Int4,
+            Type::INT8 => PgKind::Int8,
+            Type::FLOAT4 => PgKind::Float4,
+            Type::FLOAT8 => PgKind::Float8,
+            Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME => PgKind::Text,
+            Type::BYTEA => PgKind::Bytea,
+            Type::DATE => PgKind::Date,
+            Type::TIMESTAMP => PgKind::Timestamp,
+            Type::TIMESTAMPTZ => PgKind::TimestampTz,
+            Type::NUMERIC => PgKind::Numeric,
+            _ => PgKind::Other,
+        }
+    }
+}
+
+macro_rules! build_array {
+    ($rows:expr, $idx:expr, $builder:ty, $ty:ty) => {
+        build_array!($rows, $idx, $builder, $ty, |v| v)
+    };
+    ($rows:expr, $idx:expr, $builder:ty, $ty:ty, $map:expr) => {{
+        let mut builder = <$builder>::new();
+        for row in $rows {
+            let v: Option<$ty> = row
+                .try_get($idx)
+                .map_err(|e| DataFusionError::External(Box::new(e)))?;
+            builder.append_option(v.map($map));
+        }
+        Arc::new(builder.finish()) as ArrayRef
+    }};
+}
+
+#[allow(clippy::result_large_err)]
+fn rows_to_batch(rows: &[Row], columns: &[PgColumn], schema: SchemaRef) -> Result<RecordBatch> {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).expect("valid date");
+    let arrays = columns
+        .iter()
+        .enumerate()
+        .map(|(i, col)| {
+            let array = match col.kind {
+                PgKind::Bool => build_array!(rows, i, BooleanBuilder, bool),
+                PgKind::Int2 => build_array!(rows, i, Int16Builder, i16),
+                PgKind::Int4 => build_array!(rows, i, Int32Builder, i32),
+                PgKind::Int8 => build_array!(rows, i, Int64Builder, i64),
+                PgKind::Float4 => build_array!(rows, i, Float32Builder, f32),
+                PgKind::Float8 | PgKind::Numeric => build_array!(rows, i, Float64Builder, f64),
+                PgKind::Text | PgKind::Other => build_array!(rows, i, StringBuilder, String),
+                PgKind::Bytea => build_array!(rows, i, BinaryBuilder, Vec<u8>),
+                PgKind::Date => build_array!(rows, i, Date32Builder, NaiveDate, |d| {
+                    (d - epoch).num_days() as i32
+                }),
+                PgKind::Timestamp => {
+                    build_array!(rows, i, TimestampMicrosecondBuilder, NaiveDateTime, |t| {
+                        t.and_utc().timestamp_micros()
+                    })
+                }
+                PgKind::TimestampTz => {
+                    let array: ArrayRef =
+                        build_array!(rows, i, TimestampMicrosecondBuilder, DateTime<Utc>, |t| t
+                            .timestamp_micros());
+                    arrow::compute::cast(&array, &col.kind.data_type())?
+                }
+            };
+            Ok(array)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let options = RecordBatchOptions::new().with_row_count(Some(rows.len()));
+    Ok(RecordBatch::try_new_with_options(schema, arrays, &options)?)
+}
+
+/// Render a DataFusion filter as a Postgres condition. Column qualifiers are stripped since the
+/// registered dataset name usually differs from the Postgres table name.
+#[allow(clippy::result_large_err)]
+fn filter_to_sql(filter: &Expr) -> Option<String> {
+    let filter = filter
+        .clone()
+        .transform(|e| match e {
+            Expr::Column(c) => Ok(Transformed::yes(Expr::Column(c.name.into()))),
+            e => Ok(Transformed::no(e)),
+        })
+        .ok()?
+        .data;
+    let dialect = PostgreSqlDialect {};
+    let sql = Unparser::new(&dialect).expr_to_sql(&filter).ok()?;
+    Some(sql.to_string())
+}
+
+fn quote_ident(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+fn quote_table(table: &str) -> String {
+    table
+        .split('.')
+        .map(quote_ident)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::prelude::{col, lit};
+
+    #[test]
+    fn filter_to_sql_should_strip_qualifiers() {
+        let filter = col("orders.amount")
+            .gt(lit(10))
+            .and(col("status").eq(lit("paid")));
+        let sql = filter_to_sql(&filter).unwrap();
+        assert_eq!(sql, r#"(("amount" > 10) AND ("status" = 'paid'))"#);
+    }
+
+    #[test]
+    fn is_exact_should_only_allow_equivalent_filters() {
+        let columns = [
+            ("amount", PgKind::Int8),
+            ("status", PgKind::Text),
+            ("n", PgKind::Numeric),
+        ]
+        .map(|(name, kind)| PgColumn {
+            name: name.to_string(),
+            kind,
+        });
+        assert!(is_exact(
+            &columns,
+            &col("amount").gt(lit(10)).and(col("status").eq(lit("paid")))
+        ));
+        assert!(is_exact(
+            &columns,
+            &col("status").in_list(vec![lit("a"), lit("b")], false)
+        ));
+        // collations order text, integer division truncates differently, numeric is a float
+        assert!(!is_exact(&columns, &col("status").lt(lit("m"))));
+        assert!(!is_exact(&columns, &(col("amount") / lit(3)).eq(lit(1))));
+        assert!(!is_exact(&columns, &col("n").eq(lit(1))));
+    }
+
+    #[test]
+    fn quote_table_should_work() {
+        assert_eq!(quote_table("public.orders"), r#""public"."orders""#);
+        assert_eq!(quote_table(r#"we"ird"#), r#""we""ird""#);
+    }
+}
@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{
+        ArrayRef, BooleanArray, Date32Array, Float64Array, Int64Array, RecordBatch, StringArray,
+        TimestampMillisecondArray,
+    },
+    datatypes::{Field, Schema},
+};
+use chrono::DateTime;
+
+use crate::workbook::{SheetColumn, SheetTable};
+
+/// The batch of a sheet read by the workbook reader.
+pub(super) fn sheet_batch(table: SheetTable) -> anyhow::Result<RecordBatch> {
+    let arrays: Vec<ArrayRef> = table
+        .columns
+        .into_iter()
+        .map(|values| -> ArrayRef {
+            match values {
+                SheetColumn::Boolean(v) => Arc::new(BooleanArray::from(v)),
+                SheetColumn::Int(v) => Arc::new(Int64Array::from(v)),
+                SheetColumn::Float(v) => Arc::new(Float64Array::from(v)),
+                SheetColumn::Utf8(v) => Arc::new(StringArray::from(v)),
+                SheetColumn::Date(v) => Arc::new(Date32Array::from_iter(v.into_iter().map(|d| {
+                    d.map(|d| (d - DateTime::UNIX_EPOCH.date_naive()).num_days() as i32)
+                }))),
+                SheetColumn::Datetime(v) => Arc::new(TimestampMillisecondArray::from_iter(
+                    v.into_iter()
+                        .map(|dt| dt.map(|dt| dt.and_utc().timestamp_millis())),
+                )),
+            }
+        })
+        .collect();
+    let fields: Vec<_> = table
+        .names
+        .iter()
+        .zip(&arrays)
+        .map(|(name, array)| Field::new(name, array.data_type().clone(), true))
+        .collect();
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?)
+}
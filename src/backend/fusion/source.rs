@@ -0,0 +1,145 @@
+use std::{collections::HashMap, fs::File, sync::Arc};
+
+use apache_avro::{schema::DecimalSchema, Reader, Schema as AvroSchema};
+use arrow::{
+    array::{ArrayRef, RecordBatch, UInt64Array},
+    datatypes::{DataType, Field, Schema},
+};
+use orc_rust::ArrowReaderBuilder;
+
+use crate::cli::connect::DatasetConn;
+
+/// The types of the columns as the file declares them, for the formats that say more than their
+/// Arrow types: Avro logical types and ORC types.
+pub(super) fn source_types(conn: &DatasetConn) -> anyhow::Result<Option<HashMap<String, String>>> {
+    let types = match conn {
+        DatasetConn::Avro(filename) => {
+            let reader = Reader::new(File::open(filename)?)?;
+            match reader.writer_schema() {
+                AvroSchema::Record(record) => record
+                    .fields
+                    .iter()
+                    .map(|f| (f.name.clone(), avro_type(&f.schema)))
+                    .collect(),
+                _ => return Ok(None),
+            }
+        }
+        DatasetConn::Orc(filename) => {
+            let builder = ArrowReaderBuilder::try_new(File::open(filename)?)?;
+            builder
+                .file_metadata()
+                .root_data_type()
+                .children()
+                .iter()
+                .map(|c| {
+                    // nested types list their children on separate lines
+                    let t = c.data_type().to_string();
+                    (
+                        c.name().to_string(),
+                        t.split_whitespace().collect::<Vec<_>>().join(" "),
+                    )
+                })
+                .collect()
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(types))
+}
+
+/// An Avro type with its logical type, e.g. `long (timestamp-millis)`.
+fn avro_type(schema: &AvroSchema) -> String {
+    let logical = |physical: &str, logical: &str| format!("{physical} ({logical})");
+    match schema {
+        AvroSchema::Null => "null".to_string(),
+        AvroSchema::Boolean => "boolean".to_string(),
+        AvroSchema::Int => "int".to_string(),
+        AvroSchema::Long => "long".to_string(),
+        AvroSchema::Float => "float".to_string(),
+        AvroSchema::Double => "double".to_string(),
+        AvroSchema::Bytes => "bytes".to_string(),
+        AvroSchema::String => "string".to_string(),
+        AvroSchema::Array(array) => format!("array<{}>", avro_type(&array.items)),
+        AvroSchema::Map(map) => format!("map<{}>", avro_type(&map.types)),
+        AvroSchema::Union(union) => union
+            .variants()
+            .iter()
+            .map(avro_type)
+            .collect::<Vec<_>>()
+            .join(" | "),
+        AvroSchema::Record(record) => format!("record {}", record.name.name),
+        AvroSchema::Enum(e) => format!("enum {}", e.name.name),
+        AvroSchema::Fixed(fixed) => format!("fixed({})", fixed.size),
+        AvroSchema::Decimal(DecimalSchema {
+            precision,
+            scale,
+            inner,
+        }) => logical(&avro_type(inner), &format!("decimal({precision}, {scale})")),
+        AvroSchema::BigDecimal => logical("bytes", "big-decimal"),
+        AvroSchema::Uuid => logical("string", "uuid"),
+        AvroSchema::Date => logical("int", "date"),
+        AvroSchema::TimeMillis => logical("int", "time-millis"),
+        AvroSchema::TimeMicros => logical("long", "time-micros"),
+        AvroSchema::TimestampMillis => logical("long", "timestamp-millis"),
+        AvroSchema::TimestampMicros => logical("long", "timestamp-micros"),
+        AvroSchema::TimestampNanos => logical("long", "timestamp-nanos"),
+        AvroSchema::LocalTimestampMillis => logical("long", "local-timestamp-millis"),
+        AvroSchema::LocalTimestampMicros => logical("long", "local-timestamp-micros"),
+        AvroSchema::LocalTimestampNanos => logical("long", "local-timestamp-nanos"),
+        AvroSchema::Duration => logical("fixed(12)", "duration"),
+        AvroSchema::Ref { name } => name.name.clone(),
+    }
+}
+
+/// One row per stripe of an ORC file: where it starts, its rows and the size of its parts.
+pub(super) fn orc_stripes(filename: &str) -> anyhow::Result<RecordBatch> {
+    let builder = ArrowReaderBuilder::try_new(File::open(filename)?)?;
+    let stripes = builder.file_metadata().stripe_metadatas();
+    let column = |f: fn(&orc_rust::stripe::StripeMetadata) -> u64| {
+        Arc::new(UInt64Array::from_iter_values(stripes.iter().map(f))) as ArrayRef
+    };
+    let fields = [
+        "stripe",
+        "offset",
+        "rows",
+        "index_bytes",
+        "data_bytes",
+        "footer_bytes",
+    ]
+    .map(|name| Field::new(name, DataType::UInt64, false));
+    let columns = vec![
+        Arc::new(UInt64Array::from_iter_values(0..stripes.len() as u64)) as ArrayRef,
+        column(|s| s.offset()),
+        column(|s| s.number_of_rows()),
+        column(|s| s.index_length()),
+        column(|s| s.data_length()),
+        column(|s| s.footer_length()),
+    ];
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(fields.to_vec())),
+        columns,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_types_should_show_avro_logical_types() -> anyhow::Result<()> {
+        let conn = DatasetConn::Avro("assets/sample.avro".to_string());
+        let types = source_types(&conn)?.expect("avro files have source types");
+        assert_eq!(types["email"], "null | string");
+        assert_eq!(types["born"], "int (date)");
+        assert_eq!(types["created_at"], "long (timestamp-millis)");
+        Ok(())
+    }
+
+    #[test]
+    fn orc_stripes_should_list_every_stripe() -> anyhow::Result<()> {
+        let stripes = orc_stripes("assets/sample.orc")?;
+        assert_eq!(stripes.num_rows(), 3);
+        let rows = stripes.column(2).as_any().downcast_ref::<UInt64Array>();
+        assert_eq!(rows.map(|r| r.values().iter().sum::<u64>()), Some(12));
+        Ok(())
+    }
+}
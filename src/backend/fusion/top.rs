@@ -0,0 +1,118 @@
+use arrow::datatypes::DataType;
+use datafusion::{
+    common::ScalarValue,
+    dataframe::DataFrame,
+    functions_aggregate::{count::count, sum::sum_udaf},
+    logical_expr::{
+        cast, col, expr::WindowFunction, lit, Expr, ExprFunctionExt, WindowFrame, WindowFrameBound,
+        WindowFrameUnits, WindowFunctionDefinition,
+    },
+    prelude::SessionContext,
+};
+
+use crate::cli::top::TopOps;
+
+pub(super) async fn top(ctx: &SessionContext, opts: &TopOps) -> anyhow::Result<DataFrame> {
+    let df = ctx.sql(&format!("SELECT * FROM {}", opts.name)).await?;
+    let names: Vec<_> = df
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| f.name().as_str())
+        .collect();
+    opts.check_column(&names)?;
+    let field = df.schema().field_with_unqualified_name(&opts.column)?;
+    let is_list = matches!(
+        field.data_type(),
+        DataType::List(_) | DataType::LargeList(_)
+    );
+
+    let mut df = df.select_columns(&[&opts.column])?;
+    // lists are counted by element
+    if is_list {
+        df = df.unnest_columns(&[&opts.column])?;
+    }
+    let value = col(&opts.column);
+    if !opts.include_null {
+        df = df.filter(value.clone().is_not_null())?;
+    }
+
+    let order = vec![
+        col("count").sort(false, false),
+        value.clone().sort(true, false),
+    ];
+    let sum_of_counts = || {
+        Expr::WindowFunction(WindowFunction::new(
+            WindowFunctionDefinition::AggregateUDF(sum_udaf()),
+            vec![col("count")],
+        ))
+    };
+    let running = sum_of_counts()
+        .order_by(order.clone())
+        .window_frame(WindowFrame::new_bounds(
+            WindowFrameUnits::Rows,
+            WindowFrameBound::Preceding(ScalarValue::UInt64(None)),
+            WindowFrameBound::CurrentRow,
+        ))
+        .build()?;
+    let df = df
+        .aggregate(vec![value.clone()], vec![count(lit(1)).alias("count")])?
+        .select(vec![
+            value.clone(),
+            col("count"),
+            sum_of_counts().alias("total"),
+            running.alias("running"),
+        ])?
+        .sort(order)?
+        .limit(0, Some(opts.limit()))?;
+
+    let total = || cast(col("total"), DataType::Float64);
+    let percent = |e: Expr| cast(e, DataType::Float64) * lit(100.0) / total();
+    let counted = match opts.normalize {
+        true => (cast(col("count"), DataType::Float64) / total()).alias("frequency"),
+        false => col("count"),
+    };
+    Ok(df.select(vec![
+        value,
+        counted,
+        percent(col("count")).alias("percent"),
+        percent(col("running")).alias("cumulative_percent"),
+    ])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use datafusion::prelude::NdJsonReadOptions;
+
+    #[tokio::test]
+    async fn top_should_count_list_elements() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_json(
+            "users",
+            "assets/users.ndjson",
+            NdJsonReadOptions::default().file_extension(".ndjson"),
+        )
+        .await?;
+        let opts = TopOps::new(
+            "users".to_string(),
+            "recent_watched".to_string(),
+            Some(3),
+            false,
+            false,
+            None,
+        );
+
+        let batches = top(&ctx, &opts).await?.collect().await?;
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 3);
+        let schema = batches[0].schema();
+        let names: Vec<_> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["recent_watched", "count", "percent", "cumulative_percent"]
+        );
+
+        Ok(())
+    }
+}
@@ -1,3 +1,51 @@
 mod fusion;
+mod polar;
 
-pub use fusion::DataFusionBackend;
+pub use fusion::{DataFrameDescriber, DataFusionBackend};
+pub use polar::PolarsBackend;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        catalog::SessionCatalog,
+        cli::{
+            connect::{ConnectOps, DatasetConn, FileOps},
+            corr::{CorrMethod, CorrOps},
+        },
+        Backend,
+    };
+    use anyhow::Result;
+    use arrow::{array::AsArray, datatypes::Float64Type};
+    use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+
+    #[tokio::test]
+    async fn spearman_should_agree_across_engines() -> Result<()> {
+        // the null isn't in the last row, so the ranks of x change once its row is left out
+        let path = std::env::temp_dir().join("taotie_spearman_test.csv");
+        std::fs::write(&path, "x,y\n1,1\n2,\n3,2\n4,3\n")?;
+        let conn = DatasetConn::CSv(FileOps {
+            filename: path.to_string_lossy().to_string(),
+            ext: "csv".to_string(),
+            compression: FileCompressionType::UNCOMPRESSED,
+        });
+        let connect = ConnectOps::new(conn, None, "t".to_string());
+        let opts = CorrOps::new("t".to_string(), vec![], CorrMethod::Spearman, None);
+
+        let mut fusion = DataFusionBackend::new(SessionCatalog::default());
+        fusion.connect(&connect).await?;
+        let batches = fusion.corr(&opts).await?.collect().await?;
+        let fusion_xy = batches[0].column(2).as_primitive::<Float64Type>().value(0);
+
+        let mut polars = PolarsBackend::new(SessionCatalog::default());
+        polars.connect(&connect).await?;
+        let df = polars.corr(&opts).await?.collect()?;
+        let polars_xy = df.column("y")?.f64()?.get(0).unwrap_or_default();
+
+        assert!((fusion_xy - 1.0).abs() < 1e-9, "{fusion_xy}");
+        assert!((polars_xy - 1.0).abs() < 1e-9, "{polars_xy}");
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use polars::prelude::*;
+
+use super::collect;
+use crate::cli::corr::{matrix, pairs, CorrMethod, CorrOps};
+
+pub(super) async fn corr(mut lf: LazyFrame, opts: &CorrOps) -> anyhow::Result<LazyFrame> {
+    let schema = lf.collect_schema()?;
+    let available: Vec<_> = schema
+        .iter()
+        .map(|(name, dt)| (name.as_str(), dt.is_temporal() || dt.is_primitive_numeric()))
+        .collect();
+    let columns = opts.selected_columns(&available)?;
+
+    // temporal values are correlated as their number of days or time units since the epoch
+    let value = |i: usize| {
+        let name = columns[i].as_str();
+        let value = match schema.get(name).is_some_and(|dt| dt.is_temporal()) {
+            true => col(name).cast(DataType::Int64),
+            false => col(name),
+        };
+        value.cast(DataType::Float64)
+    };
+    let exprs: Vec<_> = pairs(columns.len())
+        .into_iter()
+        .enumerate()
+        .map(|(p, (i, j))| {
+            let coefficient = match opts.method {
+                // only the rows where both values are set count
+                CorrMethod::Pearson => {
+                    let both = value(i).is_not_null().and(value(j).is_not_null());
+                    let masked = |v: Expr| when(both.clone()).then(v).otherwise(lit(NULL));
+                    pearson_corr(masked(value(i)), masked(value(j)))
+                }
+                CorrMethod::Spearman => spearman_rank_corr(value(i), value(j), false),
+            };
+            coefficient.cast(DataType::Float64).alias(format!("c{p}"))
+        })
+        .collect();
+
+    let coefficients = match exprs.is_empty() {
+        true => vec![],
+        false => {
+            let df = collect(lf.select(exprs)).await?;
+            let mut coefficients = Vec::new();
+            for column in df.get_columns() {
+                coefficients.push(column.f64()?.get(0));
+            }
+            coefficients
+        }
+    };
+
+    let mut out = vec![Column::new("column".into(), &columns)];
+    for (name, values) in columns.iter().zip(matrix(columns.len(), &coefficients)) {
+        out.push(Column::new(name.into(), values));
+    }
+    Ok(DataFrame::new(out)?.lazy())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn corr_should_skip_rows_with_a_null() -> Result<()> {
+        let lf = df!(
+            "x" => [1, 2, 2, 3, 4],
+            "y" => [Some(10), Some(20), Some(20), None, Some(40)],
+            "z" => ["a", "b", "c", "d", "e"],
+        )?
+        .lazy();
+
+        for method in [CorrMethod::Pearson, CorrMethod::Spearman] {
+            let opts = CorrOps::new("t".to_string(), vec![], method, None);
+            let df = corr(lf.clone(), &opts).await?.collect()?;
+            assert_eq!(df.width(), 3);
+            let x: Vec<_> = df.column("x")?.f64()?.into_no_null_iter().collect();
+            assert!(x.iter().all(|v| (v - 1.0).abs() < 1e-9), "{x:?}");
+        }
+
+        Ok(())
+    }
+}
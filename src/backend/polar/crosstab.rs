@@ -0,0 +1,54 @@
+use polars::prelude::*;
+
+use super::collect;
+use crate::cli::crosstab::{CrosstabCell, CrosstabOps};
+
+pub(super) async fn crosstab(mut lf: LazyFrame, opts: &CrosstabOps) -> anyhow::Result<LazyFrame> {
+    let schema = lf.collect_schema()?;
+    opts.check_columns(&schema.iter_names().map(|n| n.as_str()).collect::<Vec<_>>())?;
+
+    let (row, column) = (col(opts.row.as_str()), col(opts.column.as_str()));
+    // the values are ranked in their own type, so that numbers aren't sorted as text
+    let rank = |e: Expr| {
+        let options = RankOptions {
+            method: RankMethod::Dense,
+            descending: false,
+        };
+        e.rank(options, None).cast(DataType::Int64)
+    };
+    let counts = lf
+        .group_by([row.clone(), column.clone()])
+        .agg([len().alias("count")])
+        .select([
+            row.clone().cast(DataType::String).alias("row"),
+            rank(row).alias("row_rank"),
+            column.clone().cast(DataType::String).alias("column"),
+            rank(column).alias("column_rank"),
+            col("count").cast(DataType::Int64),
+        ]);
+    let df = collect(counts).await?;
+
+    let rows = df.column("row")?.str()?;
+    let row_ranks = df.column("row_rank")?.i64()?;
+    let columns = df.column("column")?.str()?;
+    let column_ranks = df.column("column_rank")?.i64()?;
+    let counts = df.column("count")?.i64()?;
+    let cells = (0..df.height())
+        .map(|i| CrosstabCell {
+            row: rows.get(i).map(str::to_string),
+            row_rank: row_ranks.get(i),
+            column: columns.get(i).map(str::to_string),
+            column_rank: column_ranks.get(i),
+            count: counts.get(i).unwrap_or_default() as u64,
+        })
+        .collect();
+    let table = opts.table(cells)?;
+
+    let columns: Vec<_> = table
+        .header
+        .iter()
+        .zip(table.columns)
+        .map(|(name, values)| Column::new(name.into(), values))
+        .collect();
+    Ok(DataFrame::new(columns)?.lazy())
+}
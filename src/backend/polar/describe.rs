@@ -0,0 +1,251 @@
+use polars::prelude::*;
+
+use super::collect;
+use crate::cli::describe::{
+    describe_rows, format_span, ColumnKind, DescribeMethod, DescribeOps, DescribeStat, TOP_VALUES,
+};
+
+pub struct DataFrameDescriber {
+    /// The `by` columns first, then the described ones.
+    lf: LazyFrame,
+    schema: SchemaRef,
+    by: Vec<String>,
+    methods: Vec<DescribeMethod>,
+}
+
+impl DataFrameDescriber {
+    pub fn try_new(mut lf: LazyFrame, opts: &DescribeOps) -> anyhow::Result<Self> {
+        let schema = lf.collect_schema()?;
+        let names: Vec<_> = schema.iter_names().map(|n| n.as_str()).collect();
+        opts.check_columns(&names)?;
+        let mut lf = lf.select(
+            opts.selected_columns(&names)
+                .into_iter()
+                .map(col)
+                .collect::<Vec<_>>(),
+        );
+
+        Ok(Self {
+            schema: lf.collect_schema()?,
+            lf,
+            by: opts.by.clone(),
+            methods: opts.methods(),
+        })
+    }
+
+    /// Compute every statistic of every column in a single select, so the source is only
+    /// scanned once, then pivot the result rows, one per group, into one row per statistic.
+    pub async fn describe(&self) -> anyhow::Result<LazyFrame> {
+        let fields: Vec<_> = self.schema.iter().skip(self.by.len()).collect();
+        let kinds: Vec<_> = fields.iter().map(|(_, dt)| kind(dt)).collect();
+        let rows = describe_rows(&self.methods, &kinds);
+
+        let mut exprs = Vec::new();
+        for ((name, _), kind) in fields.iter().zip(&kinds) {
+            for stat in kind.stats(&self.methods) {
+                let i = rows
+                    .iter()
+                    .position(|r| *r == stat)
+                    .expect("rows cover every stat");
+                // same types as the DataFusion describer, spans are formatted when pivoting
+                let dt = match (kind, stat) {
+                    (ColumnKind::Numeric, _) | (_, DescribeStat::Span) => DataType::Float64,
+                    _ => DataType::String,
+                };
+                let expr = self::stat(stat, *kind, col((*name).clone())).cast(dt);
+                exprs.push(expr.alias(format!("{i}_{name}")));
+            }
+        }
+        let lf = match self.by.is_empty() {
+            true => self.lf.clone().select(exprs),
+            false => {
+                let by: Vec<_> = self.by.iter().map(col).collect();
+                let sort = SortMultipleOptions::default().with_maintain_order(true);
+                self.lf
+                    .clone()
+                    .group_by(&by)
+                    .agg(exprs)
+                    .sort_by_exprs(&by, sort)
+            }
+        };
+        let groups = collect(lf).await?;
+
+        // every statistic of the first group, then every statistic of the next one...
+        let indices: Vec<_> = (0..groups.height())
+            .flat_map(|g| (0..rows.len()).map(move |r| (r, g)))
+            .collect();
+        let group_indices = IdxCa::from_vec(
+            "".into(),
+            indices.iter().map(|(_, g)| *g as IdxSize).collect(),
+        );
+        let mut columns = groups
+            .select(&self.by)?
+            .take(&group_indices)?
+            .take_columns();
+        let labels: Vec<_> = indices.iter().map(|(r, _)| rows[*r].to_string()).collect();
+        columns.push(Column::new("describe".into(), labels));
+        for ((name, _), kind) in fields.iter().zip(&kinds) {
+            let cell = |r: usize| groups.column(&format!("{r}_{name}")).ok();
+            let column = match kind {
+                ColumnKind::Numeric => {
+                    let values = indices
+                        .iter()
+                        .map(|(r, g)| {
+                            Ok(cell(*r)
+                                .map(|c| c.f64())
+                                .transpose()?
+                                .and_then(|c| c.get(*g)))
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    Column::new((*name).clone(), values)
+                }
+                _ => {
+                    let values = indices
+                        .iter()
+                        .map(|(r, g)| {
+                            let Some(c) = cell(*r) else {
+                                return Ok(None);
+                            };
+                            Ok(match rows[*r] {
+                                DescribeStat::Span => c.f64()?.get(*g).map(format_span),
+                                _ => c.str()?.get(*g).map(str::to_owned),
+                            })
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    Column::new((*name).clone(), values)
+                }
+            };
+            columns.push(column);
+        }
+        Ok(DataFrame::new(columns)?.lazy())
+    }
+}
+
+fn kind(dt: &DataType) -> ColumnKind {
+    match dt {
+        DataType::String => ColumnKind::String,
+        DataType::List(inner) => ColumnKind::List {
+            numeric: inner.is_primitive_numeric(),
+        },
+        DataType::Boolean => ColumnKind::Boolean,
+        DataType::Date | DataType::Datetime(_, _) => ColumnKind::Temporal,
+        dt if dt.is_primitive_numeric() => ColumnKind::Numeric,
+        _ => ColumnKind::Other,
+    }
+}
+
+/// The expression computing `stat` for a column of the given kind. Only the stats of
+/// `ColumnKind::stats` are asked for, so lengths are only taken of strings and lists.
+fn stat(stat: DescribeStat, kind: ColumnKind, c: Expr) -> Expr {
+    let len = || match kind {
+        ColumnKind::List { .. } => c.clone().list().len(),
+        _ => c.clone().str().len_chars(),
+    };
+    // dates and timestamps in milliseconds since the epoch
+    let millis = || {
+        c.clone()
+            .cast(DataType::Datetime(TimeUnit::Milliseconds, None))
+            .cast(DataType::Int64)
+    };
+    match stat {
+        // polars can't estimate distinct lists, count them like DataFusion
+        DescribeStat::Method(DescribeMethod::ApproxDistinct) if !kind.approx_distinct() => {
+            c.n_unique()
+        }
+        DescribeStat::Method(method) => aggregate(method, c),
+        DescribeStat::MinLength => len().min(),
+        DescribeStat::MaxLength => len().max(),
+        DescribeStat::AvgLength => len().mean(),
+        DescribeStat::Empty => c.eq(lit("")).sum(),
+        DescribeStat::Top => top_values(c),
+        DescribeStat::ElementMin => c.explode().cast(DataType::Float64).min(),
+        DescribeStat::ElementMax => c.explode().cast(DataType::Float64).max(),
+        DescribeStat::ElementMean => c.explode().cast(DataType::Float64).mean(),
+        DescribeStat::True => c.sum(),
+        DescribeStat::False => c.not().sum(),
+        DescribeStat::Earliest => c.min(),
+        DescribeStat::Latest => c.max(),
+        DescribeStat::Span => {
+            (millis().max() - millis().min()).cast(DataType::Float64) / lit(1000.0)
+        }
+    }
+}
+
+fn aggregate(method: DescribeMethod, c: Expr) -> Expr {
+    match method {
+        DescribeMethod::Count => c.count(),
+        DescribeMethod::Nulls => c.null_count(),
+        DescribeMethod::NullRatio => ratio(c.null_count(), len()),
+        DescribeMethod::Min => c.min(),
+        DescribeMethod::Max => c.max(),
+        DescribeMethod::Mean => c.mean(),
+        DescribeMethod::Stddev => c.std(1),
+        DescribeMethod::Median => c.median(),
+        DescribeMethod::Percentile(p) => c.quantile(lit(p as f64 / 100.0), QuantileMethod::Linear),
+        DescribeMethod::Distinct => c.n_unique(),
+        // `n_unique` counts null as a value
+        DescribeMethod::DistinctRatio => ratio(c.clone().drop_nulls().n_unique(), c.count()),
+        DescribeMethod::ApproxDistinct => c.approx_n_unique(),
+        DescribeMethod::Mode => c.mode().sort(Default::default()).first(),
+    }
+}
+
+fn ratio(part: Expr, total: Expr) -> Expr {
+    part.cast(DataType::Float64) / total.cast(DataType::Float64)
+}
+
+/// The `TOP_VALUES` most frequent non-null values with their counts, e.g. `a (3), b (2)`. Ties
+/// are ordered by value, like DataFusion does.
+fn top_values(c: Expr) -> Expr {
+    // the runs of the sorted values are in a stable order, unlike `value_counts`, which matters
+    // as the fields below are computed separately
+    let runs = c.drop_nulls().sort(Default::default()).rle();
+    let value = runs.clone().struct_().field_by_name("value");
+    let count = runs.struct_().field_by_name("len");
+    let text = [
+        value.cast(DataType::String),
+        lit(" ("),
+        count.clone().cast(DataType::String),
+        lit(")"),
+    ];
+    concat_str(text, "", true)
+        .sort_by(
+            [count],
+            SortMultipleOptions::default()
+                .with_order_descending(true)
+                .with_maintain_order(true),
+        )
+        .head(Some(TOP_VALUES))
+        .str()
+        .join(", ", true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn data_frame_describer_should_work() -> Result<()> {
+        let lf = LazyJsonLineReader::new("assets/users.ndjson").finish()?;
+        let opts = DescribeOps::new(
+            Some("users".to_string()),
+            None,
+            vec![],
+            vec![],
+            vec![],
+            None,
+        );
+        let dfd = DataFrameDescriber::try_new(lf, &opts)?;
+
+        let df = dfd.describe().await?.collect()?;
+        // the 8 default stats, then the profiles of the string and list columns
+        assert_eq!(df.height(), 18);
+        let finished = df.column("finished")?.str()?;
+        assert_eq!(finished.get(17), Some("450289.32731092436"));
+        let name = df.column("name")?.str()?;
+        assert_eq!(name.get(9), Some("1.0"));
+
+        Ok(())
+    }
+}
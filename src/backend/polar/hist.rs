@@ -0,0 +1,128 @@
+use anyhow::Context;
+use polars::prelude::*;
+
+use super::collect;
+use crate::cli::hist::{HistOps, HistValues, Histogram};
+
+pub(super) async fn hist(mut lf: LazyFrame, opts: &HistOps) -> anyhow::Result<Histogram> {
+    let schema = lf.collect_schema()?;
+    opts.check_column(&schema.iter_names().map(|n| n.as_str()).collect::<Vec<_>>())?;
+    let data_type = schema
+        .get(opts.column.as_str())
+        .expect("the column was checked")
+        .clone();
+    let temporal = matches!(data_type, DataType::Date | DataType::Datetime(..));
+    if !temporal && !data_type.is_primitive_numeric() {
+        return Err(opts.unsupported(data_type).into());
+    }
+
+    let value = col(opts.column.as_str());
+    let number = || value.clone().cast(DataType::Float64);
+    let finite = || number().is_finite();
+    let finite_number = || number().filter(finite());
+    let stats = collect(lf.clone().select([
+        len().alias("rows"),
+        value.clone().count().alias("values"),
+        finite_number().count().alias("finite"),
+        finite_number().min().alias("min"),
+        finite_number().max().alias("max"),
+    ]))
+    .await?;
+    let (rows, values) = (int(&stats, "rows")?[0], int(&stats, "values")?[0]);
+    let nulls = (rows - values) as u64;
+    let non_finite = match temporal {
+        true => 0,
+        false => (values - int(&stats, "finite")?[0]) as u64,
+    };
+    let lf = match temporal {
+        true => lf.filter(value.clone().is_not_null()),
+        false => lf.filter(finite()),
+    };
+
+    if temporal {
+        let day = value.cast(DataType::Date).cast(DataType::Int32);
+        let df = collect(lf.group_by([day.alias("day")]).agg([len().alias("count")])).await?;
+        let days = int(&df, "day")?
+            .into_iter()
+            .zip(int(&df, "count")?)
+            .map(|(day, count)| (day as i32, count as u64))
+            .collect();
+        return Ok(Histogram {
+            values: HistValues::Days(days),
+            nulls,
+            non_finite,
+        });
+    }
+
+    let (Some(min), Some(max)) = (float(&stats, "min")?, float(&stats, "max")?) else {
+        let values = HistValues::Bins {
+            min: 0.0,
+            max: 0.0,
+            counts: vec![],
+        };
+        return Ok(Histogram {
+            values,
+            nulls,
+            non_finite,
+        });
+    };
+    // a single value still gets a bin
+    let bins = if min == max { 1 } else { opts.bins() };
+    let width = (max - min) / bins as f64;
+    let last = lit(bins as i64 - 1);
+    let bin = ((number() - lit(min)) / lit(width)).cast(DataType::Int64);
+    // the maximum falls right on the end of the last bin
+    let bin = when(bin.clone().gt_eq(last.clone()))
+        .then(last)
+        .otherwise(bin);
+    let df = collect(lf.group_by([bin.alias("bin")]).agg([len().alias("count")])).await?;
+    let mut counts = vec![0; bins];
+    for (bin, count) in int(&df, "bin")?.into_iter().zip(int(&df, "count")?) {
+        counts[bin as usize] = count as u64;
+    }
+    Ok(Histogram {
+        values: HistValues::Bins { min, max, counts },
+        nulls,
+        non_finite,
+    })
+}
+
+/// The values of a column as integers. The columns read here have no nulls, one would shift the
+/// counts against their bins, so it's an error.
+fn int(df: &DataFrame, name: &str) -> anyhow::Result<Vec<i64>> {
+    let column = df.column(name)?.cast(&DataType::Int64)?;
+    column
+        .i64()?
+        .into_iter()
+        .map(|v| v.context("Unexpected null in the histogram counts"))
+        .collect()
+}
+
+/// The first value of a column, as a float.
+fn float(df: &DataFrame, name: &str) -> PolarsResult<Option<f64>> {
+    Ok(df.column(name)?.cast(&DataType::Float64)?.f64()?.get(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn hist_should_count_dates_by_day() -> Result<()> {
+        let lf = df!("day" => [Some(19_000), Some(19_000), None, Some(19_002)])?
+            .lazy()
+            .select([col("day").cast(DataType::Date)]);
+        let opts = HistOps::new("days".to_string(), "day".to_string(), None, false);
+
+        let hist = hist(lf, &opts).await?;
+        let HistValues::Days(mut days) = hist.values else {
+            panic!("dates should be counted by day");
+        };
+        days.sort();
+        assert_eq!(days, vec![(19_000, 2), (19_002, 1)]);
+        assert_eq!(hist.nulls, 1);
+
+        Ok(())
+    }
+}
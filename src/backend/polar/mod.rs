@@ -0,0 +1,418 @@
+mod corr;
+mod crosstab;
+mod describe;
+mod hist;
+mod sheet;
+mod top;
+
+use crate::{
+    catalog::SessionCatalog,
+    cli::{
+        connect::{is_arrow_stream, DatasetConn, JsonOps},
+        corr::CorrOps,
+        create::CreateOps,
+        crosstab::CrosstabOps,
+        describe::DescribeOps,
+        explain::ExplainOps,
+        export::{ExportFormat, ExportOps},
+        hist::{HistOps, Histogram},
+        sql::is_query,
+        top::TopOps,
+    },
+    document::{is_document, read_records},
+    format::{format_batches, OutputFormat},
+    pager::Pending,
+    settings::Settings,
+    workbook::{read_sheet, unselected_sheets},
+    Backend, ErrorKind, ReplDisplay, ReplError, ReplRows,
+};
+use anyhow::{anyhow, bail};
+use arrow::ipc::reader::FileReader;
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+use describe::DataFrameDescriber;
+use polars::{prelude::*, sql::SQLContext};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::Cursor,
+};
+
+pub struct PolarsBackend {
+    /// `SQLContext::execute` needs `&mut self`, while the `Backend` queries only borrow the
+    /// backend. The backend lives on a single thread, so a `RefCell` is enough.
+    ctx: RefCell<SQLContext>,
+    /// `SQLContext` only knows lazy frames, so views and materialized tables are tracked here.
+    /// Anything not in the map is a plain table.
+    kinds: HashMap<String, &'static str>,
+    catalog: SessionCatalog,
+    settings: Settings,
+    pending: Option<Pending<Self>>,
+    last_result: Option<LazyFrame>,
+}
+
+impl PolarsBackend {
+    pub fn new(catalog: SessionCatalog) -> Self {
+        Self {
+            ctx: RefCell::new(SQLContext::new()),
+            kinds: HashMap::new(),
+            catalog,
+            settings: Settings::default(),
+            pending: None,
+            last_result: None,
+        }
+    }
+
+    fn execute(&self, sql: &str) -> anyhow::Result<LazyFrame> {
+        Ok(self.ctx.borrow_mut().execute(sql)?)
+    }
+
+    fn register(&mut self, name: &str, lf: LazyFrame, kind: Option<&'static str>) {
+        self.ctx.get_mut().register(name, lf);
+        match kind {
+            Some(kind) => self.kinds.insert(name.to_string(), kind),
+            None => self.kinds.remove(name),
+        };
+    }
+}
+
+impl Backend for PolarsBackend {
+    type DataFrame = LazyFrame;
+
+    async fn connect(&mut self, opts: &crate::cli::connect::ConnectOps) -> anyhow::Result<()> {
+        let lf = match &opts.conn {
+            DatasetConn::Postgres(_) => bail!("Postgres is not supported by the polars engine"),
+            DatasetConn::Avro(_) => bail!("Avro is not supported by the polars engine"),
+            DatasetConn::Orc(_) => bail!("ORC is not supported by the polars engine"),
+            DatasetConn::Excel(sheet_opts) => {
+                let (table, sheets) = read_sheet(sheet_opts)?;
+                self.catalog.set_sheets(&opts.name, sheets);
+                sheet::sheet_frame(table)?.lazy()
+            }
+            DatasetConn::CSv(file_opts) => {
+                if file_opts.compression != FileCompressionType::UNCOMPRESSED {
+                    bail!("Compressed csv is not supported by the polars engine");
+                }
+                LazyCsvReader::new(&file_opts.filename)
+                    .with_has_header(true)
+                    .finish()?
+            }
+            DatasetConn::Parquet(filename) => {
+                LazyFrame::scan_parquet(filename, Default::default())?
+            }
+            DatasetConn::Json(json_opts) if is_document(json_opts)? => {
+                // inferred like NDJSON, from the records written a line each
+                let mut lines = Vec::new();
+                for record in read_records(json_opts)? {
+                    serde_json::to_writer(&mut lines, &record)?;
+                    lines.push(b'\n');
+                }
+                JsonLineReader::new(Cursor::new(lines)).finish()?.lazy()
+            }
+            DatasetConn::NdJson(file_opts)
+            | DatasetConn::Json(JsonOps {
+                file: file_opts, ..
+            }) => {
+                if file_opts.compression != FileCompressionType::UNCOMPRESSED {
+                    bail!("Compressed ndjson is not supported by the polars engine");
+                }
+                LazyJsonLineReader::new(&file_opts.filename).finish()?
+            }
+            DatasetConn::Arrow(filename) if is_arrow_stream(filename)? => {
+                // only the file format can be scanned, a stream is read once into memory
+                IpcStreamReader::new(File::open(filename)?).finish()?.lazy()
+            }
+            // memory mapped, so the buffers aren't copied
+            DatasetConn::Arrow(filename) => LazyFrame::scan_ipc(filename, Default::default())?,
+        };
+        self.register(&opts.name, lf, None);
+
+        Ok(())
+    }
+
+    async fn list(&self) -> anyhow::Result<Self::DataFrame> {
+        let tables = self.ctx.borrow().get_tables();
+        let types: Vec<_> = tables
+            .iter()
+            .map(|t| self.kinds.get(t).copied().unwrap_or("table"))
+            .collect();
+        let df = df!(
+            "table_name" => tables,
+            "table_type" => types,
+        )?;
+        Ok(df.lazy())
+    }
+
+    async fn sheets(&self) -> anyhow::Result<Option<Self::DataFrame>> {
+        match unselected_sheets(&self.catalog) {
+            Some(table) => Ok(Some(sheet::sheet_frame(table)?.lazy())),
+            None => Ok(None),
+        }
+    }
+
+    async fn columns(&self) -> anyhow::Result<BTreeMap<String, Vec<String>>> {
+        let tables = self.ctx.borrow().get_tables();
+        let mut ret = BTreeMap::new();
+        for table in tables {
+            let schema = self
+                .execute(&format!("SELECT * FROM {table}"))?
+                .collect_schema()?;
+            ret.insert(table, schema.iter_names().map(|n| n.to_string()).collect());
+        }
+        Ok(ret)
+    }
+
+    async fn schema(&self, name: &str) -> anyhow::Result<Self::DataFrame> {
+        let schema = self
+            .execute(&format!("SELECT * FROM {name}"))?
+            .collect_schema()?;
+        let (names, types): (Vec<_>, Vec<_>) = schema
+            .iter()
+            .map(|(name, dt)| (name.to_string(), dt.to_string()))
+            .unzip();
+        let nullable = vec!["YES"; names.len()];
+        let df = df!(
+            "column_name" => names,
+            "data_type" => types,
+            "is_nullable" => nullable,
+        )?;
+        Ok(df.lazy())
+    }
+
+    async fn layout(&self, _name: &str) -> anyhow::Result<Option<Self::DataFrame>> {
+        Ok(None)
+    }
+
+    async fn describe(
+        &self,
+        lf: Self::DataFrame,
+        opts: &DescribeOps,
+    ) -> anyhow::Result<Self::DataFrame> {
+        let ddf = DataFrameDescriber::try_new(lf, opts)?;
+        ddf.describe().await
+    }
+
+    async fn head(&self, name: &str, size: usize) -> anyhow::Result<Self::DataFrame> {
+        self.execute(&format!("SELECT * FROM {name} LIMIT {size}"))
+    }
+
+    async fn top(&self, opts: &TopOps) -> anyhow::Result<Self::DataFrame> {
+        let lf = self.execute(&format!("SELECT * FROM {}", opts.name))?;
+        top::top(lf, opts)
+    }
+
+    async fn hist(&self, opts: &HistOps) -> anyhow::Result<Histogram> {
+        let lf = self.execute(&format!("SELECT * FROM {}", opts.name))?;
+        hist::hist(lf, opts).await
+    }
+
+    async fn corr(&self, opts: &CorrOps) -> anyhow::Result<Self::DataFrame> {
+        let lf = self.execute(&format!("SELECT * FROM {}", opts.name))?;
+        corr::corr(lf, opts).await
+    }
+
+    async fn crosstab(&self, opts: &CrosstabOps) -> anyhow::Result<Self::DataFrame> {
+        let lf = self.execute(&format!("SELECT * FROM {}", opts.name))?;
+        crosstab::crosstab(lf, opts).await
+    }
+
+    async fn sql(&self, sql: &str) -> anyhow::Result<Self::DataFrame> {
+        self.execute(sql)
+    }
+
+    async fn explain(&self, opts: &ExplainOps) -> anyhow::Result<String> {
+        // the SQL context runs a `create` or `drop` while planning it
+        if !is_query(&opts.query) {
+            let e = ReplError::new(ErrorKind::Planning, "Only a query can be explained")
+                .with_hint("explain only plans queries, run the statement with `sql`");
+            return Err(e.into());
+        }
+        let lf = self.execute(&opts.query)?;
+        // polars plans are trees already, and it doesn't expose a physical plan
+        let mut out = format!(
+            "Logical plan:\n{}\nOptimized logical plan:\n{}\n",
+            lf.describe_plan()?,
+            lf.describe_optimized_plan()?,
+        );
+        if opts.verbose {
+            let schema = lf.clone().collect_schema()?;
+            out.push_str("Schema:\n");
+            for (name, dt) in schema.iter() {
+                out.push_str(&format!("  {name}: {dt}\n"));
+            }
+        }
+        if opts.analyze {
+            // polars only times the nodes, in microseconds
+            let (df, profile) = tokio::task::spawn_blocking(move || lf.profile()).await??;
+            out.push_str(&format!("Profile:\n{profile}\n{} rows\n", df.height()));
+        }
+        Ok(out)
+    }
+
+    async fn export(&self, opts: &ExportOps) -> anyhow::Result<usize> {
+        let target = &opts.target;
+        if target.compression != FileCompressionType::UNCOMPRESSED {
+            bail!("Compressed output is not supported by the polars engine");
+        }
+
+        let mut df = collect(self.execute(&opts.query())?).await?;
+        let file = File::create(&target.filename)?;
+        match target.format {
+            ExportFormat::Csv => CsvWriter::new(file).finish(&mut df)?,
+            ExportFormat::NdJson => JsonWriter::new(file)
+                .with_json_format(JsonFormat::JsonLines)
+                .finish(&mut df)?,
+            ExportFormat::Parquet => {
+                let compression = match &opts.compression {
+                    Some(c) => parse_parquet_compression(c)?,
+                    None => ParquetCompression::default(),
+                };
+                ParquetWriter::new(file)
+                    .with_compression(compression)
+                    .with_row_group_size(opts.row_group_size)
+                    .finish(&mut df)?;
+            }
+            // the oldest layout has no string views, which other Arrow readers can't all read
+            ExportFormat::Arrow => IpcWriter::new(file)
+                .with_compat_level(CompatLevel::oldest())
+                .finish(&mut df)?,
+            ExportFormat::ArrowStream => IpcStreamWriter::new(file)
+                .with_compat_level(CompatLevel::oldest())
+                .finish(&mut df)?,
+        }
+
+        Ok(df.height())
+    }
+
+    async fn create(&mut self, opts: &CreateOps) -> anyhow::Result<()> {
+        let lf = self.execute(&opts.sql())?;
+        if opts.materialize {
+            let lf = collect(lf).await?.lazy();
+            self.register(&opts.name, lf, Some("materialized"));
+        } else {
+            self.register(&opts.name, lf, Some("view"));
+        }
+        Ok(())
+    }
+
+    async fn drop(&mut self, name: &str) -> anyhow::Result<()> {
+        if !self.ctx.get_mut().get_tables().iter().any(|t| t == name) {
+            return Err(
+                ReplError::new(ErrorKind::Planning, format!("Dataset not found: {name}")).into(),
+            );
+        }
+        self.ctx.get_mut().unregister(name);
+        self.kinds.remove(name);
+        Ok(())
+    }
+
+    fn catalog(&mut self) -> &mut SessionCatalog {
+        &mut self.catalog
+    }
+
+    fn settings(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    fn pending(&mut self) -> &mut Option<Pending<Self>> {
+        &mut self.pending
+    }
+
+    fn last_result(&mut self) -> &mut Option<Self::DataFrame> {
+        &mut self.last_result
+    }
+}
+
+/// Parse codecs in the same `name(level)` form DataFusion accepts, e.g. `zstd(3)`.
+fn parse_parquet_compression(s: &str) -> anyhow::Result<ParquetCompression> {
+    let (name, level) = match s.split_once('(') {
+        Some((name, level)) => (name, Some(level.trim_end_matches(')').parse::<i32>()?)),
+        None => (s, None),
+    };
+
+    let compression = match name.to_lowercase().as_str() {
+        "uncompressed" => ParquetCompression::Uncompressed,
+        "snappy" => ParquetCompression::Snappy,
+        "lzo" => ParquetCompression::Lzo,
+        "lz4" | "lz4_raw" => ParquetCompression::Lz4Raw,
+        "gzip" => ParquetCompression::Gzip(level.map(|l| GzipLevel::try_new(l as _)).transpose()?),
+        "brotli" => {
+            ParquetCompression::Brotli(level.map(|l| BrotliLevel::try_new(l as _)).transpose()?)
+        }
+        "zstd" => ParquetCompression::Zstd(level.map(ZstdLevel::try_new).transpose()?),
+        v => return Err(anyhow!("Invalid parquet compression: {v}")),
+    };
+    Ok(compression)
+}
+
+impl Default for PolarsBackend {
+    fn default() -> Self {
+        Self::new(SessionCatalog::default())
+    }
+}
+
+impl ReplDisplay for LazyFrame {
+    type Rows = LazyRows;
+
+    async fn rows(self) -> anyhow::Result<Self::Rows> {
+        Ok(LazyRows {
+            lf: self,
+            offset: 0,
+        })
+    }
+}
+
+/// A result that is paged by slicing the query, so polars only materializes one page at a time.
+pub struct LazyRows {
+    lf: LazyFrame,
+    offset: usize,
+}
+
+impl ReplRows for LazyRows {
+    async fn next_page(
+        &mut self,
+        format: OutputFormat,
+        max_rows: usize,
+    ) -> anyhow::Result<(String, bool)> {
+        // one extra row tells whether there's another page
+        let len = match max_rows {
+            0 => IdxSize::MAX,
+            n => (n + 1).try_into()?,
+        };
+        let mut df = collect(self.lf.clone().slice(self.offset.try_into()?, len)).await?;
+        let more = max_rows > 0 && df.height() > max_rows;
+        if more {
+            df = df.head(Some(max_rows));
+        }
+        self.offset += df.height();
+        Ok((render(df, format)?, more))
+    }
+
+    async fn count_rest(self) -> anyhow::Result<usize> {
+        let lf = self.lf.slice(self.offset.try_into()?, IdxSize::MAX);
+        let df = collect(lf.select([len()])).await?;
+        let count = df.get_columns()[0].get(0)?.extract::<usize>();
+        count.ok_or_else(|| anyhow!("Failed to count the remaining rows"))
+    }
+}
+
+/// Collect on a blocking thread. Polars can't be interrupted, but this way a cancelled command
+/// returns right away instead of waiting for the query to finish.
+async fn collect(lf: LazyFrame) -> anyhow::Result<DataFrame> {
+    Ok(tokio::task::spawn_blocking(move || lf.collect()).await??)
+}
+
+fn render(mut df: DataFrame, format: OutputFormat) -> anyhow::Result<String> {
+    if format == OutputFormat::Table {
+        return Ok(df.to_string());
+    }
+
+    // go through arrow IPC so every other format is rendered exactly like DataFusion's
+    let mut buf = Vec::new();
+    IpcWriter::new(&mut buf)
+        .with_compat_level(CompatLevel::oldest())
+        .finish(&mut df)?;
+    let reader = FileReader::try_new(Cursor::new(buf), None)?;
+    let batches = reader.collect::<Result<Vec<_>, _>>()?;
+    format_batches(&batches, format)
+}
@@ -0,0 +1,35 @@
+use chrono::DateTime;
+use polars::prelude::*;
+
+use crate::workbook::{SheetColumn, SheetTable};
+
+/// The frame of a sheet read by the workbook reader.
+pub(super) fn sheet_frame(table: SheetTable) -> anyhow::Result<DataFrame> {
+    let mut columns = Vec::with_capacity(table.columns.len());
+    for (name, values) in table.names.into_iter().zip(table.columns) {
+        let name = PlSmallStr::from(name);
+        let column = match values {
+            SheetColumn::Boolean(v) => Column::new(name, v),
+            SheetColumn::Int(v) => Column::new(name, v),
+            SheetColumn::Float(v) => Column::new(name, v),
+            SheetColumn::Utf8(v) => Column::new(name, v),
+            // temporal values are built from their days or milliseconds since the epoch
+            SheetColumn::Date(v) => {
+                let days: Vec<_> = v
+                    .iter()
+                    .map(|d| d.map(|d| (d - DateTime::UNIX_EPOCH.date_naive()).num_days() as i32))
+                    .collect();
+                Column::new(name, days).cast(&DataType::Date)?
+            }
+            SheetColumn::Datetime(v) => {
+                let millis: Vec<_> = v
+                    .iter()
+                    .map(|dt| dt.map(|dt| dt.and_utc().timestamp_millis()))
+                    .collect();
+                Column::new(name, millis).cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?
+            }
+        };
+        columns.push(column);
+    }
+    Ok(DataFrame::new(columns)?)
+}
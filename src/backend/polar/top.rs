@@ -0,0 +1,42 @@
+use polars::prelude::*;
+
+use crate::cli::top::TopOps;
+
+pub(super) fn top(mut lf: LazyFrame, opts: &TopOps) -> anyhow::Result<LazyFrame> {
+    let schema = lf.collect_schema()?;
+    opts.check_column(&schema.iter_names().map(|n| n.as_str()).collect::<Vec<_>>())?;
+
+    let value = col(opts.column.as_str());
+    let mut lf = lf.select([value.clone()]);
+    // lists are counted by element. Polars explodes an empty list to a null, DataFusion skips it
+    if let Some(DataType::List(_)) = schema.get(opts.column.as_str()) {
+        let not_empty = value.clone().list().len().gt(lit(0));
+        lf = lf
+            .filter(value.clone().is_null().or(not_empty))
+            .explode([opts.column.as_str()]);
+    }
+    if !opts.include_null {
+        lf = lf.filter(value.clone().is_not_null());
+    }
+
+    let order = SortMultipleOptions::default()
+        .with_order_descending_multi([true, false])
+        .with_nulls_last(true);
+    let total = || col("count").sum().cast(DataType::Float64);
+    let percent = |e: Expr| e.cast(DataType::Float64) * lit(100.0) / total();
+    let counted = match opts.normalize {
+        true => (col("count").cast(DataType::Float64) / total()).alias("frequency"),
+        false => col("count"),
+    };
+    Ok(lf
+        .group_by([value.clone()])
+        .agg([len().alias("count")])
+        .sort_by_exprs([col("count"), value.clone()], order)
+        .select([
+            value,
+            counted,
+            percent(col("count")).alias("percent"),
+            percent(col("count").cum_sum(false)).alias("cumulative_percent"),
+        ])
+        .limit(opts.limit() as IdxSize))
+}
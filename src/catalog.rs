@@ -0,0 +1,337 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cli::{
+        connect::{ConnectOps, DatasetConn},
+        create::CreateOps,
+    },
+    workbook::SheetRange,
+    Backend,
+};
+
+/// A change to the datasets registered in the session. Entries are kept in order so derived
+/// datasets are restored after the tables they query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum CatalogEntry {
+    Dataset {
+        name: String,
+        conn: DatasetConn,
+        table: Option<String>,
+    },
+    Derived {
+        name: String,
+        sql: String,
+        materialize: bool,
+    },
+    /// Kept only while a derived dataset registered in between may depend on the dropped one.
+    Dropped { name: String },
+}
+
+/// The datasets registered in the current session. Every change is written to the autosave file
+/// (if any), so the session survives a restart.
+#[derive(Debug, Default)]
+pub struct SessionCatalog {
+    entries: Vec<CatalogEntry>,
+    autosave: Option<PathBuf>,
+    /// The sheets of the workbooks connected without `--sheet`, read when they're connected.
+    sheets: BTreeMap<String, Vec<SheetRange>>,
+}
+
+impl CatalogEntry {
+    pub fn name(&self) -> &str {
+        match self {
+            CatalogEntry::Dataset { name, .. } => name,
+            CatalogEntry::Derived { name, .. } => name,
+            CatalogEntry::Dropped { name } => name,
+        }
+    }
+
+    async fn restore<T: Backend>(&self, backend: &mut T) -> anyhow::Result<()> {
+        match self.clone() {
+            CatalogEntry::Dataset { name, conn, table } => {
+                backend.connect(&ConnectOps::new(conn, table, name)).await
+            }
+            CatalogEntry::Derived {
+                name,
+                sql,
+                materialize,
+            } => {
+                backend
+                    .create(&CreateOps::new(name, materialize, vec![sql]))
+                    .await
+            }
+            CatalogEntry::Dropped { name } => backend.drop(&name).await,
+        }
+    }
+}
+
+impl SessionCatalog {
+    pub fn new(autosave: Option<PathBuf>) -> Self {
+        Self {
+            entries: Vec::new(),
+            autosave,
+            sheets: BTreeMap::new(),
+        }
+    }
+
+    /// Names of the datasets currently registered, in registration order.
+    pub fn names(&self) -> Vec<&str> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(i, e)| {
+                !matches!(e, CatalogEntry::Dropped { .. })
+                    && !self.entries[i + 1..].iter().any(|l| l.name() == e.name())
+            })
+            .map(|(_, e)| e.name())
+            .collect()
+    }
+
+    /// How a dataset was connected, unless it's derived or was dropped since.
+    pub fn dataset(&self, name: &str) -> Option<&DatasetConn> {
+        match self.entries.iter().rev().find(|e| e.name() == name)? {
+            CatalogEntry::Dataset { conn, .. } => Some(conn),
+            _ => None,
+        }
+    }
+
+    /// The sheets of a workbook dataset, empty unless it was connected without `--sheet`.
+    pub fn sheets(&self, name: &str) -> &[SheetRange] {
+        self.sheets.get(name).map_or(&[], |s| s.as_slice())
+    }
+
+    pub fn set_sheets(&mut self, name: &str, sheets: Vec<SheetRange>) {
+        self.sheets.insert(name.to_string(), sheets);
+    }
+
+    pub fn autosave_file(&self) -> Option<&Path> {
+        self.autosave.as_deref()
+    }
+
+    pub fn add(&mut self, entry: CatalogEntry) {
+        // `connect` has just read the sheets of a workbook connected without `--sheet`, anything
+        // else registered under the name has none
+        let lists_sheets = matches!(
+            &entry,
+            CatalogEntry::Dataset { conn: DatasetConn::Excel(opts), .. } if opts.sheet.is_none()
+        );
+        if !lists_sheets {
+            self.sheets.remove(entry.name());
+        }
+        self.entries.push(entry);
+        self.compact();
+        self.autosave();
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.sheets.remove(name);
+        self.entries.push(CatalogEntry::Dropped {
+            name: name.to_string(),
+        });
+        self.compact();
+        self.autosave();
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.sheets.clear();
+        self.autosave();
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&self.entries)?)?;
+        Ok(())
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> anyhow::Result<Vec<CatalogEntry>> {
+        let data = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    /// Forget entries that no longer affect the restored session.
+    fn compact(&mut self) {
+        while let Some(i) = (0..self.entries.len()).find(|i| self.is_obsolete(*i)) {
+            self.entries.remove(i);
+        }
+    }
+
+    fn is_obsolete(&self, i: usize) -> bool {
+        let entry = &self.entries[i];
+        if let CatalogEntry::Dropped { name } = entry {
+            // the dropped dataset is already gone from the history
+            return !self.entries[..i].iter().any(|e| e.name() == name);
+        }
+
+        // a replaced or dropped dataset is only needed if something may have been derived from it
+        let later = &self.entries[i + 1..];
+        match later.iter().position(|e| e.name() == entry.name()) {
+            Some(j) => !later[..j]
+                .iter()
+                .any(|e| matches!(e, CatalogEntry::Derived { .. })),
+            None => false,
+        }
+    }
+
+    fn autosave(&self) {
+        if let Some(path) = &self.autosave {
+            if let Err(e) = self.save(path) {
+                eprintln!("Failed to save session to {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Register the datasets of a saved session into the backend. Entries that fail to restore (e.g.
+/// the file was moved) are reported, and kept in the catalog so they come back once they can. The
+/// autosave file is only written once every entry is restored, never half way.
+pub async fn restore<T: Backend>(
+    backend: &mut T,
+    entries: Vec<CatalogEntry>,
+) -> (usize, Vec<String>) {
+    let autosave = backend.catalog().autosave.take();
+    let mut restored = SessionCatalog::default();
+    let mut errors = Vec::new();
+    for entry in entries {
+        match entry.restore(backend).await {
+            Ok(()) => restored.add(entry.clone()),
+            Err(e) => errors.push(format!("{}: {}", entry.name(), e)),
+        }
+        backend.catalog().add(entry);
+    }
+    let catalog = backend.catalog();
+    catalog.autosave = autosave;
+    catalog.autosave();
+    (restored.names().len(), errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::DataFusionBackend;
+    use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+
+    #[test]
+    fn catalog_entries_should_round_trip() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join("taotie_session_test.json");
+        let mut catalog = SessionCatalog::new(Some(path.clone()));
+        catalog.add(CatalogEntry::Dataset {
+            name: "users".to_string(),
+            conn: DatasetConn::NdJson(crate::cli::connect::FileOps {
+                filename: "assets/users.ndjson.gz".to_string(),
+                ext: "ndjson".to_string(),
+                compression: FileCompressionType::GZIP,
+            }),
+            table: None,
+        });
+        catalog.add(CatalogEntry::Derived {
+            name: "recent".to_string(),
+            sql: "select * from users".to_string(),
+            materialize: false,
+        });
+
+        let entries = SessionCatalog::read(&path)?;
+        assert_eq!(entries.len(), 2);
+        match &entries[0] {
+            CatalogEntry::Dataset {
+                conn: DatasetConn::NdJson(opts),
+                ..
+            } => assert_eq!(opts.compression, FileCompressionType::GZIP),
+            e => panic!("unexpected entry: {e:?}"),
+        }
+        assert_eq!(entries[1].name(), "recent");
+
+        fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restore_should_keep_failed_entries() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join("taotie_restore_test.json");
+        let dataset = |name: &str, filename: &str| CatalogEntry::Dataset {
+            name: name.to_string(),
+            conn: DatasetConn::Parquet(filename.to_string()),
+            table: None,
+        };
+        let entries = vec![
+            dataset("missing", "assets/missing.parquet"),
+            dataset("sample", "assets/sample.parquet"),
+        ];
+        let mut backend = DataFusionBackend::new(SessionCatalog::new(Some(path.clone())));
+
+        let (restored, errors) = restore(&mut backend, entries).await;
+        assert_eq!((restored, errors.len()), (1, 1));
+        assert_eq!(backend.catalog().names(), ["missing", "sample"]);
+        assert_eq!(SessionCatalog::read(&path)?.len(), 2);
+
+        fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn catalog_should_keep_dropped_sources_of_derived_datasets() {
+        let derived = |name: &str| CatalogEntry::Derived {
+            name: name.to_string(),
+            sql: "select 1".to_string(),
+            materialize: true,
+        };
+        let mut catalog = SessionCatalog::default();
+        catalog.add(derived("a"));
+        catalog.add(derived("b"));
+        catalog.remove("b");
+        assert_eq!(catalog.entries.len(), 1);
+
+        catalog.remove("a");
+        assert!(catalog.entries.is_empty());
+
+        catalog.add(derived("a"));
+        catalog.add(derived("b"));
+        catalog.remove("a");
+        assert_eq!(catalog.entries.len(), 3);
+        assert_eq!(catalog.names(), vec!["b"]);
+    }
+
+    #[test]
+    fn catalog_should_forget_replaced_and_dropped_sheets() {
+        let sheets = vec![SheetRange {
+            sheet: "Sales".to_string(),
+            range: Some("A1:G4".to_string()),
+        }];
+        let workbook = |sheet: Option<&str>| CatalogEntry::Dataset {
+            name: "wb".to_string(),
+            conn: DatasetConn::Excel(crate::cli::connect::SheetOps {
+                filename: "assets/sample.xlsx".to_string(),
+                sheet: sheet.map(|s| s.to_string()),
+                range: None,
+                header: true,
+            }),
+            table: None,
+        };
+        let mut catalog = SessionCatalog::default();
+        catalog.set_sheets("wb", sheets.clone());
+        catalog.add(workbook(None));
+        assert_eq!(catalog.sheets("wb"), sheets);
+
+        catalog.add(workbook(Some("Sales")));
+        assert!(catalog.sheets("wb").is_empty());
+
+        catalog.set_sheets("wb", sheets.clone());
+        catalog.add(workbook(None));
+        catalog.remove("wb");
+        assert!(catalog.sheets("wb").is_empty());
+
+        catalog.set_sheets("wb", sheets);
+        catalog.clear();
+        assert!(catalog.sheets("wb").is_empty());
+    }
+}
@@ -1,37 +1,102 @@
-use clap::{ArgMatches, Parser};
+use clap::{Args, Parser};
 use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io::Read, path::Path, sync::LazyLock};
 
-use crate::{CmdExecutor, ReplContext, ReplMsg};
+use crate::{catalog::CatalogEntry, workbook::parse_range, CmdExecutor};
 
-use super::ReplResult;
+const COMPRESSION_EXTS: [(&str, FileCompressionType); 4] = [
+    ("gz", FileCompressionType::GZIP),
+    ("bz2", FileCompressionType::BZIP2),
+    ("xz", FileCompressionType::XZ),
+    ("zstd", FileCompressionType::ZSTD),
+];
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DatasetConn {
     Postgres(String),
     CSv(FileOps),
     Parquet(String),
     NdJson(FileOps),
+    /// A JSON document whose records are in an array, or NDJSON in a `.json` file.
+    Json(JsonOps),
+    /// Arrow IPC in the file (Feather v2) or the stream format.
+    Arrow(String),
+    Avro(String),
+    Orc(String),
+    /// A sheet of an Excel or OpenDocument workbook.
+    Excel(SheetOps),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileOps {
     pub filename: String,
     pub ext: String,
+    #[serde(with = "compression_serde")]
     pub compression: FileCompressionType,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonOps {
+    pub file: FileOps,
+    /// The JSON pointer to the array of records, the whole document when not given.
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SheetOps {
+    pub filename: String,
+    /// The first sheet when not given.
+    pub sheet: Option<String>,
+    /// The whole sheet when not given.
+    pub range: Option<String>,
+    /// Whether the first row holds the column names.
+    pub header: bool,
+}
+
 #[derive(Parser, Debug)]
 pub struct ConnectOps {
-    #[arg( value_parser = verify_conn, help = "Connection string to the dataset, could be postgres of local file (support: csv, parquet, json)")]
+    #[arg( value_parser = verify_conn, help = "Connection string to the dataset, could be postgres of local file (support: csv, parquet, json, arrow, avro, orc, xlsx, ods)")]
     pub conn: DatasetConn,
 
-    #[arg(short, help = "If database, the name of the table")]
+    #[arg(short, long, help = "If database, the name of the table")]
     pub table: Option<String>,
 
+    #[arg(
+        long,
+        value_parser = verify_path,
+        help = "If JSON document, the JSON pointer to the array of records, e.g. /data/items"
+    )]
+    pub path: Option<String>,
+
+    /// Moved into `conn` when the command runs, like `path`.
+    #[command(flatten)]
+    pub sheet: SheetArgs,
+
     #[arg(help = "The name of the dataset")]
     pub name: String,
 }
 
+#[derive(Args, Debug, Default)]
+pub struct SheetArgs {
+    #[arg(
+        long,
+        help = "If spreadsheet, the sheet to read, the first one by default"
+    )]
+    pub sheet: Option<String>,
+
+    #[arg(long, value_parser = verify_range, help = "If spreadsheet, the cells to read, e.g. A1:F200")]
+    pub range: Option<String>,
+
+    #[arg(
+        long,
+        help = "If spreadsheet, read the first row as data rather than column names"
+    )]
+    pub no_header: bool,
+}
+
 fn verify_conn(s: &str) -> Result<DatasetConn, String> {
     let conn_str = s.to_string();
 
@@ -43,76 +108,267 @@ fn verify_conn(s: &str) -> Result<DatasetConn, String> {
         return Ok(DatasetConn::Parquet(conn_str));
     }
 
-    let exts = conn_str.rsplit('.');
-    let count = exts.clone().count() - 1;
-    let mut exts = exts.take(count);
-    let ext1 = exts.next();
-    let ext2 = exts.next();
-    match (ext1, ext2) {
-        (Some(ext1), Some(ext2)) => {
-            let compression = match ext1 {
-                "gz" => FileCompressionType::GZIP,
-                "bz2" => FileCompressionType::BZIP2,
-                "xz" => FileCompressionType::XZ,
-                "zstd" => FileCompressionType::ZSTD,
-                v => return Err(format!("invalid compression type: {}", v)),
-            };
-
-            let opts = FileOps {
-                filename: s.to_string(),
-                ext: ext2.to_string(),
-                compression,
-            };
-
-            match ext1 {
-                "csv" => Ok(DatasetConn::CSv(opts)),
-                "json" | "jsonl" | "ndjson" => Ok(DatasetConn::NdJson(opts)),
-                v => Err(format!("Invalid file extension: {v}")),
-            }
+    let (ext, compression) = parse_file_ext(s)?;
+    let opts = FileOps {
+        filename: conn_str,
+        ext: ext.clone(),
+        compression,
+    };
+
+    match ext.as_str() {
+        "csv" => Ok(DatasetConn::CSv(opts)),
+        "json" => Ok(DatasetConn::Json(JsonOps {
+            file: opts,
+            path: None,
+        })),
+        "jsonl" | "ndjson" => Ok(DatasetConn::NdJson(opts)),
+        "arrow" | "arrows" | "feather" | "ipc" | "avro" | "orc" | "xlsx" | "xlsm" | "xlsb"
+        | "xls" | "ods"
+            if opts.compression != FileCompressionType::UNCOMPRESSED =>
+        {
+            Err(format!("{ext} files can't be compressed as a whole: {s}"))
         }
-        (Some(ext1), None) => {
-            let opts = FileOps {
-                filename: s.to_string(),
-                ext: ext1.to_string(),
-                compression: FileCompressionType::UNCOMPRESSED,
-            };
-
-            match ext1 {
-                "csv" => Ok(DatasetConn::CSv(opts)),
-                "json" | "jsonl" | "ndjson" => Ok(DatasetConn::NdJson(opts)),
-                v => Err(format!("Invalid file extension: {v}")),
-            }
+        "arrow" | "arrows" | "feather" | "ipc" => Ok(DatasetConn::Arrow(opts.filename)),
+        "avro" => Ok(DatasetConn::Avro(opts.filename)),
+        "orc" => Ok(DatasetConn::Orc(opts.filename)),
+        "xlsx" | "xlsm" | "xlsb" | "xls" | "ods" => Ok(DatasetConn::Excel(SheetOps {
+            filename: opts.filename,
+            sheet: None,
+            range: None,
+            header: true,
+        })),
+        v => Err(format!("Invalid file extension: {v}")),
+    }
+}
+
+fn verify_path(s: &str) -> Result<String, String> {
+    match s.starts_with('/') {
+        true => Ok(s.to_string()),
+        false => Err(format!(
+            "Invalid JSON pointer: {s}, expected a path like /data/items"
+        )),
+    }
+}
+
+fn verify_range(s: &str) -> Result<String, String> {
+    parse_range(s).map(|_| s.to_uppercase())
+}
+
+/// Whether an Arrow IPC file is in the stream format rather than the file format, which starts
+/// with the `ARROW1` magic. Both share the extensions in practice.
+pub fn is_arrow_stream(filename: &str) -> std::io::Result<bool> {
+    let mut magic = Vec::new();
+    File::open(filename)?.take(6).read_to_end(&mut magic)?;
+    Ok(magic != b"ARROW1")
+}
+
+/// Split a file name into its format extension and compression, e.g. `users.ndjson.gz` gives
+/// `("ndjson", GZIP)`.
+pub fn parse_file_ext(filename: &str) -> Result<(String, FileCompressionType), String> {
+    let name = Path::new(filename)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| format!("Invalid file name: {filename}"))?;
+    // skip the file stem, what remains are the extensions
+    let exts: Vec<_> = name.split('.').skip(1).collect();
+
+    match exts.as_slice() {
+        [.., ext, last] => match compression_from_ext(last) {
+            Some(compression) => Ok((ext.to_string(), compression)),
+            None => Ok((last.to_string(), FileCompressionType::UNCOMPRESSED)),
+        },
+        [ext] => Ok((ext.to_string(), FileCompressionType::UNCOMPRESSED)),
+        [] => Err(format!("Missing file extension: {filename}")),
+    }
+}
+
+fn compression_from_ext(ext: &str) -> Option<FileCompressionType> {
+    COMPRESSION_EXTS
+        .iter()
+        .find(|(e, _)| *e == ext)
+        .map(|(_, compression)| *compression)
+}
+
+fn compression_to_ext(compression: FileCompressionType) -> Option<&'static str> {
+    COMPRESSION_EXTS
+        .iter()
+        .find(|(_, c)| *c == compression)
+        .map(|(e, _)| *e)
+}
+
+/// `FileCompressionType` isn't serializable, so it's stored as its file extension (empty when
+/// uncompressed).
+mod compression_serde {
+    use super::*;
+    use serde::{de::Error, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(c: &FileCompressionType, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(compression_to_ext(*c).unwrap_or_default())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<FileCompressionType, D::Error> {
+        let ext = String::deserialize(d)?;
+        if ext.is_empty() {
+            return Ok(FileCompressionType::UNCOMPRESSED);
         }
-        _ => Err(format!("Invalid Connection string: {s}")),
+        compression_from_ext(&ext)
+            .ok_or_else(|| D::Error::custom(format!("invalid compression type: {ext}")))
     }
 }
 
-pub fn connect(args: ArgMatches, context: &mut ReplContext) -> ReplResult {
-    let conn = args
-        .get_one::<DatasetConn>("conn")
-        .expect("Connection string is required")
-        .to_owned();
+// the password in the user info of a connection URL
+static PASSWORD_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(postgres(?:ql)?://[^:@/]*):[^@/]*@").expect("valid regex"));
 
-    let table = args.get_one::<String>("table").map(|s| s.to_owned());
-    let name = args
-        .get_one::<String>("name")
-        .expect("Name is required")
-        .to_owned();
+impl DatasetConn {
+    /// The same connection without a Postgres password, which isn't written to the session file.
+    /// The restored dataset reads it from `PGPASSWORD` instead.
+    pub fn without_password(&self) -> Self {
+        match self {
+            DatasetConn::Postgres(conn_str) => {
+                let (base, query) = conn_str.split_once('?').unwrap_or((conn_str, ""));
+                let mut stripped = PASSWORD_RE.replace(base, "$1@").into_owned();
+                let params: Vec<_> = query
+                    .split('&')
+                    .filter(|p| !p.is_empty() && !p.starts_with("password="))
+                    .collect();
+                if !params.is_empty() {
+                    stripped = format!("{stripped}?{}", params.join("&"));
+                }
+                DatasetConn::Postgres(stripped)
+            }
+            _ => self.clone(),
+        }
+    }
 
-    let ret = ReplMsg::new(ConnectOps::new(conn, table, name));
+    /// The same connection with file paths made absolute, so it still works from another
+    /// working directory.
+    pub fn to_absolute(&self) -> Self {
+        let absolute = |f: &str| match std::path::absolute(f) {
+            Ok(path) => path.to_string_lossy().to_string(),
+            Err(_) => f.to_string(),
+        };
+        match self {
+            DatasetConn::Postgres(_) => self.clone(),
+            DatasetConn::CSv(opts) => DatasetConn::CSv(FileOps {
+                filename: absolute(&opts.filename),
+                ..opts.clone()
+            }),
+            DatasetConn::Parquet(filename) => DatasetConn::Parquet(absolute(filename)),
+            DatasetConn::NdJson(opts) => DatasetConn::NdJson(FileOps {
+                filename: absolute(&opts.filename),
+                ..opts.clone()
+            }),
+            DatasetConn::Json(opts) => DatasetConn::Json(JsonOps {
+                file: FileOps {
+                    filename: absolute(&opts.file.filename),
+                    ..opts.file.clone()
+                },
+                ..opts.clone()
+            }),
+            DatasetConn::Arrow(filename) => DatasetConn::Arrow(absolute(filename)),
+            DatasetConn::Avro(filename) => DatasetConn::Avro(absolute(filename)),
+            DatasetConn::Orc(filename) => DatasetConn::Orc(absolute(filename)),
+            DatasetConn::Excel(opts) => DatasetConn::Excel(SheetOps {
+                filename: absolute(&opts.filename),
+                ..opts.clone()
+            }),
+        }
+    }
+}
 
-    Ok(context.send(ret.0, ret.1))
+impl FileOps {
+    /// The extension including the compression suffix, e.g. `csv.gz`.
+    pub fn file_extension(&self) -> String {
+        match COMPRESSION_EXTS
+            .iter()
+            .find(|(_, c)| *c == self.compression)
+        {
+            Some((e, _)) => format!("{}.{}", self.ext, e),
+            None => self.ext.clone(),
+        }
+    }
 }
 
 impl ConnectOps {
     pub fn new(conn: DatasetConn, table: Option<String>, name: String) -> Self {
-        Self { conn, table, name }
+        Self {
+            conn,
+            table,
+            path: None,
+            sheet: SheetArgs::default(),
+            name,
+        }
     }
 }
 
 impl CmdExecutor for ConnectOps {
-    async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+    async fn execute<T: crate::Backend>(mut self, backend: &mut T) -> anyhow::Result<String> {
+        // like `--table`, the path and sheet settings only apply to the connections they're meant for
+        match &mut self.conn {
+            DatasetConn::Json(opts) => opts.path = self.path.take(),
+            DatasetConn::Excel(opts) => {
+                opts.sheet = self.sheet.sheet.take();
+                opts.range = self.sheet.range.take();
+                opts.header = !self.sheet.no_header;
+            }
+            _ => {}
+        }
         backend.connect(&self).await?;
+        backend.catalog().add(CatalogEntry::Dataset {
+            name: self.name.clone(),
+            conn: self.conn.to_absolute().without_password(),
+            table: self.table,
+        });
         Ok(format!("Connected to dataset: {}", self.name))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_file_ext_should_work() {
+        let (ext, compression) = parse_file_ext("./assets/users.ndjson.gz").unwrap();
+        assert_eq!(ext, "ndjson");
+        assert_eq!(compression, FileCompressionType::GZIP);
+
+        let (ext, compression) = parse_file_ext("assets/person.csv").unwrap();
+        assert_eq!(ext, "csv");
+        assert_eq!(compression, FileCompressionType::UNCOMPRESSED);
+
+        assert!(parse_file_ext("assets/person").is_err());
+    }
+
+    #[test]
+    fn without_password_should_strip_credentials() {
+        let stripped = |s: &str| match DatasetConn::Postgres(s.into()).without_password() {
+            DatasetConn::Postgres(s) => s,
+            conn => panic!("unexpected conn: {conn:?}"),
+        };
+        assert_eq!(
+            stripped("postgres://me:s3cr%40t@db:5432/shop?sslmode=disable&password=x"),
+            "postgres://me@db:5432/shop?sslmode=disable"
+        );
+        assert_eq!(stripped("postgres://me@db/shop"), "postgres://me@db/shop");
+        assert_eq!(
+            stripped("postgres://db/shop?password=pw"),
+            "postgres://db/shop"
+        );
+    }
+
+    #[test]
+    fn verify_conn_should_accept_binary_formats() {
+        for file in ["a.arrow", "a.arrows", "a.feather", "a.ipc"] {
+            assert!(matches!(verify_conn(file), Ok(DatasetConn::Arrow(f)) if f == file));
+        }
+        assert!(verify_conn("a.arrow.gz").is_err());
+        assert!(matches!(verify_conn("a.avro"), Ok(DatasetConn::Avro(_))));
+        assert!(matches!(verify_conn("a.orc"), Ok(DatasetConn::Orc(_))));
+        assert!(matches!(verify_conn("a.xlsx"), Ok(DatasetConn::Excel(opts)) if opts.header));
+        assert!(matches!(verify_conn("a.ods"), Ok(DatasetConn::Excel(_))));
+        assert!(matches!(verify_conn("a.json.gz"), Ok(DatasetConn::Json(_))));
+        assert!(matches!(verify_conn("a.jsonl"), Ok(DatasetConn::NdJson(_))));
+    }
+}
@@ -0,0 +1,140 @@
+use clap::{Parser, ValueEnum};
+
+use crate::{format::OutputFormat, pager, CmdExecutor, ErrorKind, ReplError};
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CorrMethod {
+    /// Linear correlation of the values.
+    #[default]
+    Pearson,
+    /// Pearson correlation of the ranks of the values, ties get their average rank.
+    Spearman,
+}
+
+#[derive(Parser, Debug)]
+pub struct CorrOps {
+    #[arg(help = "The name of the dataset")]
+    pub name: String,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Only correlate these columns, all the numeric and temporal ones by default"
+    )]
+    pub columns: Vec<String>,
+
+    #[arg(long, value_enum, default_value_t, help = "Correlation coefficient")]
+    pub method: CorrMethod,
+
+    #[arg(long, value_enum, help = "Output format, overriding `set format`")]
+    pub format: Option<OutputFormat>,
+}
+
+impl CorrOps {
+    pub fn new(
+        name: String,
+        columns: Vec<String>,
+        method: CorrMethod,
+        format: Option<OutputFormat>,
+    ) -> Self {
+        Self {
+            name,
+            columns,
+            method,
+            format,
+        }
+    }
+
+    /// The columns to correlate, given all the columns and whether they're numeric or temporal.
+    pub fn selected_columns(&self, available: &[(&str, bool)]) -> Result<Vec<String>, ReplError> {
+        let selected: Vec<_> = match self.columns.is_empty() {
+            true => available
+                .iter()
+                .filter(|(_, supported)| *supported)
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            false => self.columns.clone(),
+        };
+        for column in &selected {
+            match available.iter().find(|(name, _)| name == column) {
+                Some((_, true)) => {}
+                Some((_, false)) => {
+                    return Err(ReplError::new(
+                        ErrorKind::Planning,
+                        format!("Can't correlate {column}, it isn't numeric or temporal"),
+                    )
+                    .with_hint("`crosstab` counts the values of other columns"))
+                }
+                None => {
+                    return Err(ReplError::new(
+                        ErrorKind::Planning,
+                        format!("Column not found: {column}"),
+                    )
+                    .with_hint(format!("run `schema {}` to see the columns", self.name)))
+                }
+            }
+        }
+        if selected.is_empty() {
+            return Err(ReplError::new(
+                ErrorKind::Planning,
+                format!("{} has no numeric or temporal column", self.name),
+            ));
+        }
+        Ok(selected)
+    }
+}
+
+impl CmdExecutor for CorrOps {
+    async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let df = backend.corr(&self).await?;
+        pager::display(backend, df, self.format).await
+    }
+}
+
+/// The pairs of columns to correlate, `(i, j)` with `i < j`.
+pub fn pairs(n: usize) -> Vec<(usize, usize)> {
+    (0..n)
+        .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+        .collect()
+}
+
+/// The columns of the correlation matrix, from the coefficients of the `pairs(n)` in order.
+pub fn matrix(n: usize, coefficients: &[Option<f64>]) -> Vec<Vec<Option<f64>>> {
+    let mut matrix = vec![vec![None; n]; n];
+    for (i, row) in matrix.iter_mut().enumerate() {
+        row[i] = Some(1.0);
+    }
+    for ((i, j), c) in pairs(n).into_iter().zip(coefficients) {
+        matrix[i][j] = *c;
+        matrix[j][i] = *c;
+    }
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matrix_should_be_symmetric() {
+        assert_eq!(pairs(3), vec![(0, 1), (0, 2), (1, 2)]);
+        let m = matrix(3, &[Some(0.5), None, Some(-1.0)]);
+        assert_eq!(m[0], vec![Some(1.0), Some(0.5), None]);
+        assert_eq!(m[2], vec![None, Some(-1.0), Some(1.0)]);
+    }
+
+    #[test]
+    fn selected_columns_should_check_types() {
+        let available = [("name", false), ("age", true), ("born", true)];
+        let opts = CorrOps::new("person".into(), vec![], CorrMethod::Pearson, None);
+        assert_eq!(opts.selected_columns(&available).unwrap(), ["age", "born"]);
+
+        let opts = CorrOps::new(
+            "person".into(),
+            vec!["name".into()],
+            CorrMethod::Pearson,
+            None,
+        );
+        assert!(opts.selected_columns(&available).is_err());
+    }
+}
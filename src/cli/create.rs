@@ -0,0 +1,76 @@
+use clap::Parser;
+
+use crate::{catalog::CatalogEntry, CmdExecutor};
+
+#[derive(Parser, Debug)]
+pub struct CreateOps {
+    #[arg(help = "The name of the new dataset")]
+    pub name: String,
+
+    #[arg(
+        long,
+        help = "Materialize the result in memory instead of creating a view"
+    )]
+    pub materialize: bool,
+
+    #[arg(
+        required = true,
+        num_args = 1..,
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        help = "`as` followed by the SQL query"
+    )]
+    pub query: Vec<String>,
+}
+
+impl CreateOps {
+    pub fn new(name: String, materialize: bool, query: Vec<String>) -> Self {
+        Self {
+            name,
+            materialize,
+            query,
+        }
+    }
+
+    /// The SQL query, without the leading `as`.
+    pub fn sql(&self) -> String {
+        let query = match self.query.first() {
+            Some(first) if first.eq_ignore_ascii_case("as") => &self.query[1..],
+            _ => &self.query[..],
+        };
+        query.join(" ")
+    }
+}
+
+impl CmdExecutor for CreateOps {
+    async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        backend.create(&self).await?;
+        backend.catalog().add(CatalogEntry::Derived {
+            name: self.name.clone(),
+            sql: self.sql(),
+            materialize: self.materialize,
+        });
+        let kind = match self.materialize {
+            true => "materialized table",
+            false => "view",
+        };
+        Ok(format!("Created {kind}: {}", self.name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_ops_sql_should_strip_as() {
+        let query = ["AS", "select", "*", "from", "users"]
+            .map(String::from)
+            .to_vec();
+        let opts = CreateOps::new("u".to_string(), false, query);
+        assert_eq!(opts.sql(), "select * from users");
+
+        let opts = CreateOps::new("u".to_string(), false, vec!["select 1".to_string()]);
+        assert_eq!(opts.sql(), "select 1");
+    }
+}
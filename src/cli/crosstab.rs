@@ -0,0 +1,222 @@
+use clap::Parser;
+
+use crate::{format::OutputFormat, pager, CmdExecutor, ErrorKind, ReplError};
+
+/// Most distinct values of the column shown across, each one is a column of the table.
+pub const MAX_CROSSTAB_COLUMNS: usize = 50;
+
+/// Most distinct values of the column shown down, each one is a row of the table.
+pub const MAX_CROSSTAB_ROWS: usize = 10_000;
+
+#[derive(Parser, Debug)]
+pub struct CrosstabOps {
+    #[arg(help = "The name of the dataset")]
+    pub name: String,
+
+    #[arg(help = "The column whose values are the rows")]
+    pub row: String,
+
+    #[arg(help = "The column whose values are the columns")]
+    pub column: String,
+
+    #[arg(long, value_enum, help = "Output format, overriding `set format`")]
+    pub format: Option<OutputFormat>,
+}
+
+/// The count of rows sharing a pair of values, computed by the backend. The values are shown as
+/// strings and sorted by their rank in their own column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrosstabCell {
+    pub row: Option<String>,
+    pub row_rank: Option<i64>,
+    pub column: Option<String>,
+    pub column_rank: Option<i64>,
+    pub count: u64,
+}
+
+/// A contingency table, every cell a count with its share of all the rows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Crosstab {
+    pub header: Vec<String>,
+    /// The columns of the table, each of the same length.
+    pub columns: Vec<Vec<String>>,
+}
+
+impl CrosstabOps {
+    pub fn new(name: String, row: String, column: String, format: Option<OutputFormat>) -> Self {
+        Self {
+            name,
+            row,
+            column,
+            format,
+        }
+    }
+
+    /// Make sure both columns are in `available`.
+    pub fn check_columns(&self, available: &[&str]) -> Result<(), ReplError> {
+        match [&self.row, &self.column]
+            .into_iter()
+            .find(|c| !available.contains(&c.as_str()))
+        {
+            None => Ok(()),
+            Some(missing) => Err(ReplError::new(
+                ErrorKind::Planning,
+                format!("Column not found: {missing}"),
+            )
+            .with_hint(format!("run `schema {}` to see the columns", self.name))),
+        }
+    }
+
+    /// Lay the counts out as a table, with the totals of each row and column.
+    pub fn table(&self, cells: Vec<CrosstabCell>) -> Result<Crosstab, ReplError> {
+        // the dense ranks of the values are their positions, nulls are unranked or ranked last
+        let column_len = ranked_len(cells.iter().map(|c| c.column_rank));
+        if column_len > MAX_CROSSTAB_COLUMNS {
+            return Err(ReplError::new(
+                ErrorKind::Planning,
+                format!(
+                    "{} has {column_len} distinct values, more than the {MAX_CROSSTAB_COLUMNS} columns a table can show",
+                    self.column,
+                ),
+            )
+            .with_hint(format!(
+                "swap the columns, or run `top {} {}` to count its values",
+                self.name, self.column
+            )));
+        }
+        let row_len = ranked_len(cells.iter().map(|c| c.row_rank));
+        if row_len > MAX_CROSSTAB_ROWS {
+            return Err(ReplError::new(
+                ErrorKind::Planning,
+                format!(
+                    "{} has {row_len} distinct values, more than the {MAX_CROSSTAB_ROWS} rows a table can show",
+                    self.row,
+                ),
+            )
+            .with_hint(format!(
+                "run `top {} {}` to count its values",
+                self.name, self.row
+            )));
+        }
+
+        let (mut rows, mut columns) = (vec![None; row_len], vec![None; column_len]);
+        let mut counts = vec![vec![0u64; column_len]; row_len];
+        for cell in &cells {
+            let (r, c) = (
+                position(cell.row_rank, row_len),
+                position(cell.column_rank, column_len),
+            );
+            rows[r] = cell.row.clone();
+            columns[c] = cell.column.clone();
+            counts[r][c] += cell.count;
+        }
+        let total: u64 = cells.iter().map(|c| c.count).sum();
+        let share = |count: u64| match count {
+            0 => "0".to_string(),
+            _ => format!("{count} ({:.1}%)", count as f64 * 100.0 / total as f64),
+        };
+        let label = |v: &Option<String>| v.clone().unwrap_or_else(|| "null".to_string());
+
+        let mut header = vec![format!("{} \\ {}", self.row, self.column)];
+        for name in columns.iter().map(label) {
+            // a table can't have two columns of the same name, e.g. a value `total`, or a null
+            // and the text "null"
+            let mut unique = name.clone();
+            let mut n = 1;
+            while unique == "total" || header.contains(&unique) {
+                n += 1;
+                unique = format!("{name}_{n}");
+            }
+            header.push(unique);
+        }
+        header.push("total".to_string());
+
+        let mut table = vec![rows.iter().map(label).collect::<Vec<_>>()];
+        table[0].push("total".to_string());
+        for c in 0..columns.len() {
+            let mut column: Vec<_> = counts.iter().map(|row| share(row[c])).collect();
+            column.push(share(counts.iter().map(|row| row[c]).sum()));
+            table.push(column);
+        }
+        let mut totals: Vec<_> = counts.iter().map(|row| share(row.iter().sum())).collect();
+        totals.push(share(total));
+        table.push(totals);
+
+        Ok(Crosstab {
+            header,
+            columns: table,
+        })
+    }
+}
+
+/// How many distinct values dense ranks stand for, a null one included.
+fn ranked_len(ranks: impl Iterator<Item = Option<i64>> + Clone) -> usize {
+    let ranked = ranks.clone().flatten().max().unwrap_or_default() as usize;
+    ranked + ranks.into_iter().any(|r| r.is_none()) as usize
+}
+
+/// Where a value goes among `len` values by its dense rank, the unranked null last.
+fn position(rank: Option<i64>, len: usize) -> usize {
+    rank.map_or(len - 1, |r| r as usize - 1)
+}
+
+impl CmdExecutor for CrosstabOps {
+    async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let df = backend.crosstab(&self).await?;
+        pager::display(backend, df, self.format).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(row: &str, row_rank: i64, column: Option<&str>, count: u64) -> CrosstabCell {
+        CrosstabCell {
+            row: Some(row.to_string()),
+            row_rank: Some(row_rank),
+            column: column.map(|c| c.to_string()),
+            column_rank: column.map(|c| c.len() as i64),
+            count,
+        }
+    }
+
+    #[test]
+    fn table_should_add_totals() {
+        let opts = CrosstabOps::new("t".into(), "a".into(), "b".into(), None);
+        let cells = vec![
+            cell("y", 2, Some("xx"), 1),
+            cell("x", 1, Some("x"), 2),
+            cell("y", 2, None, 1),
+        ];
+
+        let table = opts.table(cells).unwrap();
+        assert_eq!(table.header, ["a \\ b", "x", "xx", "null", "total"]);
+        assert_eq!(table.columns[0], ["x", "y", "total"]);
+        assert_eq!(table.columns[1], ["2 (50.0%)", "0", "2 (50.0%)"]);
+        assert_eq!(table.columns[4], ["2 (50.0%)", "2 (50.0%)", "4 (100.0%)"]);
+    }
+
+    #[test]
+    fn table_should_have_unique_header() {
+        let opts = CrosstabOps::new("t".into(), "a".into(), "b".into(), None);
+        let cells = [
+            (Some("null"), Some(1)),
+            (Some("total"), Some(2)),
+            (None, None),
+        ]
+        .into_iter()
+        .map(|(column, column_rank)| CrosstabCell {
+            column: column.map(|c| c.to_string()),
+            column_rank,
+            ..cell("x", 1, None, 1)
+        })
+        .collect();
+
+        let table = opts.table(cells).unwrap();
+        assert_eq!(
+            table.header,
+            ["a \\ b", "null", "total_2", "null_2", "total"]
+        );
+    }
+}
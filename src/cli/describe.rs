@@ -1,35 +1,480 @@
-use clap::{ArgMatches, Parser};
+use std::{fmt, str::FromStr};
 
-use crate::{CmdExecutor, ReplContext, ReplDisplay, ReplMsg};
+use clap::Parser;
 
-use super::ReplResult;
+use crate::{format::OutputFormat, pager, CmdExecutor, ErrorKind, ReplError};
+
+/// A statistic `describe` can compute for each column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DescribeMethod {
+    Count,
+    Nulls,
+    /// Nulls over all rows.
+    NullRatio,
+    Min,
+    Max,
+    Mean,
+    Stddev,
+    Median,
+    /// The given percentile, e.g. `p25`.
+    Percentile(u8),
+    Distinct,
+    /// Distinct values over non-null values, 1 for a column of unique values.
+    DistinctRatio,
+    ApproxDistinct,
+    Mode,
+}
+
+/// The statistics shown when `--stats` isn't given.
+pub const DEFAULT_STATS: &[DescribeMethod] = &[
+    DescribeMethod::Count,
+    DescribeMethod::Nulls,
+    DescribeMethod::NullRatio,
+    DescribeMethod::Min,
+    DescribeMethod::Max,
+    DescribeMethod::Mean,
+    DescribeMethod::Stddev,
+    DescribeMethod::Median,
+];
+
+/// How many of the most frequent values the `top` row of a string column shows.
+pub const TOP_VALUES: usize = 3;
+
+/// How `describe` profiles a column, depending on its type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Numeric,
+    String,
+    /// Lists of numbers also get statistics of their elements.
+    List {
+        numeric: bool,
+    },
+    Boolean,
+    /// Dates and timestamps.
+    Temporal,
+    /// Anything else, only counted.
+    Other,
+}
+
+/// A row of the `describe` output: one of the `--stats`, or a statistic specific to a column kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DescribeStat {
+    Method(DescribeMethod),
+    /// Lengths of strings, element counts of lists.
+    MinLength,
+    MaxLength,
+    AvgLength,
+    /// Number of empty strings.
+    Empty,
+    /// The most frequent values with their counts.
+    Top,
+    ElementMin,
+    ElementMax,
+    ElementMean,
+    True,
+    False,
+    Earliest,
+    Latest,
+    /// Time between the earliest and the latest value.
+    Span,
+}
+
+/// The rows added after the `--stats` ones, in the order they're shown.
+const KIND_STATS: &[DescribeStat] = &[
+    DescribeStat::Method(DescribeMethod::Nulls),
+    DescribeStat::Method(DescribeMethod::NullRatio),
+    DescribeStat::Method(DescribeMethod::Distinct),
+    DescribeStat::Method(DescribeMethod::DistinctRatio),
+    DescribeStat::MinLength,
+    DescribeStat::MaxLength,
+    DescribeStat::AvgLength,
+    DescribeStat::Empty,
+    DescribeStat::Top,
+    DescribeStat::ElementMin,
+    DescribeStat::ElementMax,
+    DescribeStat::ElementMean,
+    DescribeStat::True,
+    DescribeStat::False,
+    DescribeStat::Earliest,
+    DescribeStat::Latest,
+    DescribeStat::Span,
+];
 
 #[derive(Parser, Debug)]
 pub struct DescribeOps {
-    #[arg(help = "The name of the dataset")]
-    pub name: String,
-}
+    #[arg(help = "The name of the dataset, the result of the last `sql` query if omitted")]
+    pub name: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "name",
+        help = "Describe the result of this SQL query"
+    )]
+    pub sql: Option<String>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Statistics to compute, left empty for columns they don't apply to: count, nulls, null_ratio, min, max, mean, stddev, median, p<N> (e.g. p25), distinct, distinct_ratio, approx_distinct, mode"
+    )]
+    pub stats: Vec<DescribeMethod>,
 
-pub fn describe(args: ArgMatches, context: &mut ReplContext) -> ReplResult {
-    let name = args
-        .get_one::<String>("name")
-        .expect("Name is required")
-        .to_owned();
+    #[arg(long, value_delimiter = ',', help = "Only describe these columns")]
+    pub columns: Vec<String>,
 
-    let ret = ReplMsg::new(DescribeOps::new(name));
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Describe each group of rows sharing the values of these columns"
+    )]
+    pub by: Vec<String>,
 
-    Ok(context.send(ret.0, ret.1))
+    #[arg(long, value_enum, help = "Output format, overriding `set format`")]
+    pub format: Option<OutputFormat>,
 }
 
 impl DescribeOps {
-    pub fn new(name: String) -> Self {
-        Self { name }
+    pub fn new(
+        name: Option<String>,
+        sql: Option<String>,
+        stats: Vec<DescribeMethod>,
+        columns: Vec<String>,
+        by: Vec<String>,
+        format: Option<OutputFormat>,
+    ) -> Self {
+        Self {
+            name,
+            sql,
+            stats,
+            columns,
+            by,
+            format,
+        }
+    }
+
+    /// The query whose result is described: the whole dataset or the `--sql` query. Without
+    /// either, it's the result of the last `sql` query.
+    pub fn query(&self) -> Option<String> {
+        match (&self.name, &self.sql) {
+            (Some(name), _) => Some(format!("SELECT * FROM {name}")),
+            (None, Some(sql)) => Some(sql.clone()),
+            (None, None) => None,
+        }
+    }
+
+    /// The statistics to compute, the defaults if none were asked for.
+    pub fn methods(&self) -> Vec<DescribeMethod> {
+        match self.stats.is_empty() {
+            true => DEFAULT_STATS.to_vec(),
+            false => self.stats.clone(),
+        }
+    }
+
+    /// Make sure every column asked for with `--columns` and `--by` is one of `available`.
+    pub fn check_columns(&self, available: &[&str]) -> Result<(), ReplError> {
+        match self
+            .columns
+            .iter()
+            .chain(&self.by)
+            .find(|c| !available.contains(&c.as_str()))
+        {
+            Some(c) => {
+                let e = ReplError::new(ErrorKind::Planning, format!("Column not found: {c}"));
+                Err(match &self.name {
+                    Some(name) => e.with_hint(format!("run `schema {name}` to see the columns")),
+                    None => e,
+                })
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// The `--by` columns followed by the columns to describe: those asked for with `--columns`,
+    /// or all of `available`, but the `--by` ones.
+    pub fn selected_columns<'a>(&'a self, available: &[&'a str]) -> Vec<&'a str> {
+        let described = match self.columns.is_empty() {
+            true => available.to_vec(),
+            false => self.columns.iter().map(String::as_str).collect(),
+        };
+        self.by
+            .iter()
+            .map(String::as_str)
+            .chain(
+                described
+                    .into_iter()
+                    .filter(|c| !self.by.iter().any(|b| b == c)),
+            )
+            .collect()
+    }
+}
+
+impl ColumnKind {
+    /// The statistics of a column of this kind. Numeric columns get every method asked for,
+    /// the others only those that make sense for their values, plus their own profile.
+    pub fn stats(self, methods: &[DescribeMethod]) -> Vec<DescribeStat> {
+        use DescribeStat::*;
+
+        let mut stats: Vec<_> = methods
+            .iter()
+            .filter(|m| self.supports(**m))
+            .map(|m| Method(*m))
+            .collect();
+        let profile = match self {
+            ColumnKind::Numeric | ColumnKind::Other => vec![],
+            ColumnKind::String => vec![
+                Method(DescribeMethod::Distinct),
+                Method(DescribeMethod::DistinctRatio),
+                MinLength,
+                MaxLength,
+                AvgLength,
+                Empty,
+                Top,
+            ],
+            ColumnKind::List { numeric: false } => vec![MinLength, MaxLength, AvgLength],
+            ColumnKind::List { numeric: true } => vec![
+                MinLength,
+                MaxLength,
+                AvgLength,
+                ElementMin,
+                ElementMax,
+                ElementMean,
+            ],
+            ColumnKind::Boolean => vec![
+                Method(DescribeMethod::Nulls),
+                Method(DescribeMethod::NullRatio),
+                True,
+                False,
+            ],
+            ColumnKind::Temporal => vec![Earliest, Latest, Span],
+        };
+        for stat in profile {
+            if !stats.contains(&stat) {
+                stats.push(stat);
+            }
+        }
+        stats
+    }
+
+    fn supports(self, method: DescribeMethod) -> bool {
+        match method {
+            DescribeMethod::Count
+            | DescribeMethod::Nulls
+            | DescribeMethod::NullRatio
+            | DescribeMethod::Distinct
+            | DescribeMethod::DistinctRatio
+            | DescribeMethod::ApproxDistinct => true,
+            DescribeMethod::Mode => !matches!(self, ColumnKind::List { .. } | ColumnKind::Other),
+            _ => self == ColumnKind::Numeric,
+        }
+    }
+
+    /// Neither engine estimates the distinct values of everything, the others are counted.
+    pub fn approx_distinct(self) -> bool {
+        matches!(self, ColumnKind::Numeric | ColumnKind::String)
+    }
+}
+
+/// The rows of the `describe` output for columns of the given kinds: the methods asked for,
+/// then the statistics specific to the kinds, in the order of `KIND_STATS`.
+pub fn describe_rows(methods: &[DescribeMethod], kinds: &[ColumnKind]) -> Vec<DescribeStat> {
+    let mut rows: Vec<_> = methods.iter().map(|m| DescribeStat::Method(*m)).collect();
+    let stats: Vec<_> = kinds.iter().flat_map(|k| k.stats(methods)).collect();
+    for stat in KIND_STATS {
+        if stats.contains(stat) && !rows.contains(stat) {
+            rows.push(*stat);
+        }
+    }
+    rows
+}
+
+/// Render a span in seconds as days and `hh:mm:ss`.
+pub fn format_span(seconds: f64) -> String {
+    let total = seconds.round() as i64;
+    let (days, rest) = (total / 86400, total % 86400);
+    let time = format!(
+        "{:02}:{:02}:{:02}",
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    );
+    match days {
+        0 => time,
+        days => format!("{days}d {time}"),
     }
 }
 
 impl CmdExecutor for DescribeOps {
     async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
-        let df = backend.describe(&self.name).await?;
-        df.display().await
+        let df = match self.query() {
+            Some(query) => backend.sql(&query).await?,
+            None => backend.last_result().clone().ok_or_else(|| {
+                ReplError::new(ErrorKind::Planning, "No result to describe").with_hint(
+                    "give a dataset name or `--sql`, or run a `sql` query returning rows first",
+                )
+            })?,
+        };
+        let df = backend.describe(df, &self).await?;
+        pager::display(backend, df, self.format).await
+    }
+}
+
+impl FromStr for DescribeMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let method = match s.to_lowercase().as_str() {
+            "count" => DescribeMethod::Count,
+            "nulls" => DescribeMethod::Nulls,
+            "null_ratio" => DescribeMethod::NullRatio,
+            "min" => DescribeMethod::Min,
+            "max" => DescribeMethod::Max,
+            "mean" => DescribeMethod::Mean,
+            "stddev" => DescribeMethod::Stddev,
+            "median" => DescribeMethod::Median,
+            "distinct" => DescribeMethod::Distinct,
+            "distinct_ratio" => DescribeMethod::DistinctRatio,
+            "approx_distinct" => DescribeMethod::ApproxDistinct,
+            "mode" => DescribeMethod::Mode,
+            v => match v.strip_prefix('p').and_then(|p| p.parse::<u8>().ok()) {
+                Some(p) if p <= 100 => DescribeMethod::Percentile(p),
+                _ => return Err(format!("Invalid statistic: {s}")),
+            },
+        };
+        Ok(method)
+    }
+}
+
+impl fmt::Display for DescribeMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DescribeMethod::Count => write!(f, "count"),
+            DescribeMethod::Nulls => write!(f, "nulls"),
+            DescribeMethod::NullRatio => write!(f, "null_ratio"),
+            DescribeMethod::Min => write!(f, "min"),
+            DescribeMethod::Max => write!(f, "max"),
+            DescribeMethod::Mean => write!(f, "mean"),
+            DescribeMethod::Stddev => write!(f, "stddev"),
+            DescribeMethod::Median => write!(f, "median"),
+            DescribeMethod::Percentile(p) => write!(f, "p{p}"),
+            DescribeMethod::Distinct => write!(f, "distinct"),
+            DescribeMethod::DistinctRatio => write!(f, "distinct_ratio"),
+            DescribeMethod::ApproxDistinct => write!(f, "approx_distinct"),
+            DescribeMethod::Mode => write!(f, "mode"),
+        }
+    }
+}
+
+impl fmt::Display for DescribeStat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DescribeStat::Method(m) => write!(f, "{m}"),
+            DescribeStat::MinLength => write!(f, "min_length"),
+            DescribeStat::MaxLength => write!(f, "max_length"),
+            DescribeStat::AvgLength => write!(f, "avg_length"),
+            DescribeStat::Empty => write!(f, "empty"),
+            DescribeStat::Top => write!(f, "top"),
+            DescribeStat::ElementMin => write!(f, "element_min"),
+            DescribeStat::ElementMax => write!(f, "element_max"),
+            DescribeStat::ElementMean => write!(f, "element_mean"),
+            DescribeStat::True => write!(f, "true"),
+            DescribeStat::False => write!(f, "false"),
+            DescribeStat::Earliest => write!(f, "earliest"),
+            DescribeStat::Latest => write!(f, "latest"),
+            DescribeStat::Span => write!(f, "span"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describe_method_should_parse() {
+        let methods: Vec<DescribeMethod> = "count,p25,P99,approx_distinct,null_ratio"
+            .split(',')
+            .map(|s| s.parse().unwrap())
+            .collect();
+        assert_eq!(
+            methods,
+            vec![
+                DescribeMethod::Count,
+                DescribeMethod::Percentile(25),
+                DescribeMethod::Percentile(99),
+                DescribeMethod::ApproxDistinct,
+                DescribeMethod::NullRatio,
+            ]
+        );
+        assert!("p101".parse::<DescribeMethod>().is_err());
+        assert!("average".parse::<DescribeMethod>().is_err());
+    }
+
+    #[test]
+    fn describe_rows_should_follow_column_kinds() {
+        let methods = [DescribeMethod::Count, DescribeMethod::Mean];
+        let rows = describe_rows(&methods, &[ColumnKind::Numeric]);
+        assert_eq!(rows.len(), 2);
+
+        let rows = describe_rows(&methods, &[ColumnKind::Temporal, ColumnKind::Boolean]);
+        let labels: Vec<_> = rows.iter().map(|r| r.to_string()).collect();
+        assert_eq!(
+            labels,
+            vec![
+                "count",
+                "mean",
+                "nulls",
+                "null_ratio",
+                "true",
+                "false",
+                "earliest",
+                "latest",
+                "span"
+            ]
+        );
+        assert!(!ColumnKind::Boolean
+            .stats(&methods)
+            .contains(&DescribeStat::Method(DescribeMethod::Mean)));
+    }
+
+    #[test]
+    fn format_span_should_work() {
+        assert_eq!(format_span(59.6), "00:01:00");
+        assert_eq!(format_span(2.0 * 86400.0 + 3723.0), "2d 01:02:03");
+    }
+
+    #[test]
+    fn selected_columns_should_put_by_first() {
+        let available = ["a", "b", "c"];
+        let by = vec!["b".to_string()];
+        let opts = DescribeOps::new(
+            Some("t".to_string()),
+            None,
+            vec![],
+            vec![],
+            by.clone(),
+            None,
+        );
+        assert_eq!(opts.selected_columns(&available), vec!["b", "a", "c"]);
+
+        let columns = vec!["c".to_string(), "b".to_string()];
+        let opts = DescribeOps::new(Some("t".to_string()), None, vec![], columns, by, None);
+        assert_eq!(opts.selected_columns(&available), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn query_should_prefer_name_and_sql() {
+        let ops = |name: Option<&str>, sql: Option<&str>| {
+            let (name, sql) = (name.map(str::to_owned), sql.map(str::to_owned));
+            DescribeOps::new(name, sql, vec![], vec![], vec![], None)
+        };
+        assert_eq!(
+            ops(Some("t"), None).query().as_deref(),
+            Some("SELECT * FROM t")
+        );
+        assert_eq!(
+            ops(None, Some("select 2")).query().as_deref(),
+            Some("select 2")
+        );
+        assert_eq!(ops(None, None).query(), None);
     }
 }
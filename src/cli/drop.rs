@@ -0,0 +1,27 @@
+use clap::Parser;
+
+use crate::CmdExecutor;
+
+#[derive(Parser, Debug)]
+pub struct DropOps {
+    #[arg(help = "The name of the dataset")]
+    pub name: String,
+}
+
+impl DropOps {
+    pub fn new(name: String) -> Self {
+        Self { name }
+    }
+}
+
+impl CmdExecutor for DropOps {
+    async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let dropped = backend.drop(&self.name).await;
+        // a dataset that failed to restore is only in the catalog
+        if dropped.is_err() && !backend.catalog().names().contains(&self.name.as_str()) {
+            dropped?;
+        }
+        backend.catalog().remove(&self.name);
+        Ok(format!("Dropped dataset: {}", self.name))
+    }
+}
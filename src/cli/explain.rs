@@ -0,0 +1,31 @@
+use clap::Parser;
+
+use crate::CmdExecutor;
+
+#[derive(Debug, Parser)]
+pub struct ExplainOps {
+    #[arg(help = "SQL query to explain")]
+    pub query: String,
+
+    #[arg(long, help = "Run the query and show the metrics of every operator")]
+    pub analyze: bool,
+
+    #[arg(long, help = "Show the schema of every plan node")]
+    pub verbose: bool,
+}
+
+impl ExplainOps {
+    pub fn new(query: String, analyze: bool, verbose: bool) -> Self {
+        Self {
+            query,
+            analyze,
+            verbose,
+        }
+    }
+}
+
+impl CmdExecutor for ExplainOps {
+    async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        backend.explain(&self).await
+    }
+}
@@ -0,0 +1,146 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+
+use crate::CmdExecutor;
+
+use super::connect::parse_file_ext;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    NdJson,
+    Parquet,
+    /// Arrow IPC file format, also read as Feather v2.
+    Arrow,
+    /// Arrow IPC stream format, written batch by batch without a footer.
+    ArrowStream,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportTarget {
+    pub filename: String,
+    pub format: ExportFormat,
+    pub compression: FileCompressionType,
+}
+
+#[derive(Parser, Debug)]
+pub struct ExportOps {
+    #[arg(help = "The name of the dataset, or a SQL query to export")]
+    pub source: String,
+
+    #[arg(value_parser = verify_target, help = "Output file, the format is picked from the extension (support: csv, ndjson, parquet, arrow, arrows for the arrow stream format)")]
+    pub target: ExportTarget,
+
+    #[arg(
+        long,
+        help = "Parquet compression codec, e.g. snappy, gzip(6), zstd(3)"
+    )]
+    pub compression: Option<String>,
+
+    #[arg(long, help = "Max number of rows in a Parquet row group")]
+    pub row_group_size: Option<usize>,
+
+    #[arg(long, help = "Overwrite the output file if it exists")]
+    pub overwrite: bool,
+}
+
+fn verify_target(s: &str) -> Result<ExportTarget, String> {
+    let (ext, compression) = parse_file_ext(s)?;
+    let format = match ext.as_str() {
+        "csv" => ExportFormat::Csv,
+        "json" | "jsonl" | "ndjson" => ExportFormat::NdJson,
+        "parquet" => ExportFormat::Parquet,
+        "arrow" | "feather" | "ipc" => ExportFormat::Arrow,
+        "arrows" => ExportFormat::ArrowStream,
+        v => return Err(format!("Invalid file extension: {v}")),
+    };
+
+    if compression != FileCompressionType::UNCOMPRESSED
+        && matches!(
+            format,
+            ExportFormat::Parquet | ExportFormat::Arrow | ExportFormat::ArrowStream
+        )
+    {
+        return Err(format!("{ext} files can't be compressed as a whole: {s}"));
+    }
+
+    Ok(ExportTarget {
+        filename: s.to_string(),
+        format,
+        compression,
+    })
+}
+
+impl ExportOps {
+    pub fn new(
+        source: String,
+        target: ExportTarget,
+        compression: Option<String>,
+        row_group_size: Option<usize>,
+        overwrite: bool,
+    ) -> Self {
+        Self {
+            source,
+            target,
+            compression,
+            row_group_size,
+            overwrite,
+        }
+    }
+
+    /// The query to export. A bare dataset name exports the whole dataset.
+    pub fn query(&self) -> String {
+        match self.source.contains(char::is_whitespace) {
+            true => self.source.clone(),
+            false => format!("SELECT * FROM {}", self.source),
+        }
+    }
+}
+
+impl CmdExecutor for ExportOps {
+    async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        if self.target.format != ExportFormat::Parquet
+            && (self.compression.is_some() || self.row_group_size.is_some())
+        {
+            anyhow::bail!("--compression and --row-group-size only apply to parquet files");
+        }
+
+        let filename = self.target.filename.clone();
+        let path = Path::new(&filename);
+        if path.exists() && !self.overwrite {
+            anyhow::bail!("{filename} already exists, use --overwrite to replace it");
+        }
+
+        // written next to the target and renamed over it once complete, so a failed or
+        // cancelled export leaves the old file as it was
+        let temp = TempFile::next_to(path)?;
+        let mut opts = self;
+        opts.target.filename = temp.0.to_string_lossy().to_string();
+        let rows = backend.export(&opts).await?;
+        std::fs::rename(&temp.0, path)?;
+        Ok(format!("Exported {rows} rows to: {filename}"))
+    }
+}
+
+/// A file removed when dropped, unless it was renamed.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    /// A hidden file in the same directory, ending with the whole name of `path` so the
+    /// extensions still tell the format.
+    fn next_to(path: &Path) -> anyhow::Result<Self> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("Invalid file name: {}", path.display()))?;
+        let temp = format!(".taotie-{}-{}", std::process::id(), name.to_string_lossy());
+        Ok(Self(path.with_file_name(temp)))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
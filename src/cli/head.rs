@@ -1,6 +1,5 @@
-use super::ReplResult;
-use crate::{CmdExecutor, ReplContext, ReplDisplay, ReplMsg};
-use clap::{ArgMatches, Parser};
+use crate::{format::OutputFormat, pager, CmdExecutor};
+use clap::Parser;
 
 #[derive(Parser, Debug)]
 pub struct HeadOps {
@@ -9,29 +8,20 @@ pub struct HeadOps {
 
     #[arg(short, help = "Number of rows to show")]
     pub n: Option<usize>,
-}
-
-pub fn head(args: ArgMatches, context: &mut ReplContext) -> ReplResult {
-    let name = args
-        .get_one::<String>("name")
-        .expect("Name is required")
-        .to_owned();
-    let n = args.get_one::<usize>("n").copied();
-
-    let ret = ReplMsg::new(HeadOps::new(name, n));
 
-    Ok(context.send(ret.0, ret.1))
+    #[arg(long, value_enum, help = "Output format, overriding `set format`")]
+    pub format: Option<OutputFormat>,
 }
 
 impl HeadOps {
-    pub fn new(name: String, n: Option<usize>) -> Self {
-        Self { name, n }
+    pub fn new(name: String, n: Option<usize>, format: Option<OutputFormat>) -> Self {
+        Self { name, n, format }
     }
 }
 
 impl CmdExecutor for HeadOps {
     async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
         let df = backend.head(&self.name, self.n.unwrap_or(5)).await?;
-        df.display().await
+        pager::display(backend, df, self.format).await
     }
 }
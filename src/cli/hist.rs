@@ -0,0 +1,272 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use chrono::{Datelike, Months, NaiveDate, TimeDelta};
+use clap::Parser;
+
+use crate::{CmdExecutor, ErrorKind, ReplError};
+
+/// Width of the longest bar, in characters.
+const BAR_WIDTH: usize = 40;
+/// Days from 0001-01-01 to 1970-01-01.
+const EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
+#[derive(Parser, Debug)]
+pub struct HistOps {
+    #[arg(help = "The name of the dataset")]
+    pub name: String,
+
+    #[arg(help = "The numeric, date or timestamp column to draw")]
+    pub column: String,
+
+    #[arg(
+        long,
+        help = "Number of bins, at most that many days, weeks, months or years for dates"
+    )]
+    pub bins: Option<usize>,
+
+    #[arg(long, help = "Scale the bars logarithmically")]
+    pub log: bool,
+}
+
+/// The counts `hist` draws, computed by the backend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub values: HistValues,
+    pub nulls: u64,
+    /// NaN and infinite numbers, left out like the nulls.
+    pub non_finite: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HistValues {
+    /// Counts of the equal-width bins from `min` to `max`, the last bin includes `max`.
+    Bins {
+        min: f64,
+        max: f64,
+        counts: Vec<u64>,
+    },
+    /// Counts per day, in days since the epoch. They're bucketed when drawing.
+    Days(Vec<(i32, u64)>),
+}
+
+/// The period dates are bucketed by: the finest one that fits in the bins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Period {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl HistOps {
+    pub fn new(name: String, column: String, bins: Option<usize>, log: bool) -> Self {
+        Self {
+            name,
+            column,
+            bins,
+            log,
+        }
+    }
+
+    /// How many bins to draw, 20 by default.
+    pub fn bins(&self) -> usize {
+        self.bins.unwrap_or(20).max(1)
+    }
+
+    /// Make sure the column is one of `available`.
+    pub fn check_column(&self, available: &[&str]) -> Result<(), ReplError> {
+        match available.contains(&self.column.as_str()) {
+            true => Ok(()),
+            false => Err(ReplError::new(
+                ErrorKind::Planning,
+                format!("Column not found: {}", self.column),
+            )
+            .with_hint(format!("run `schema {}` to see the columns", self.name))),
+        }
+    }
+
+    /// The error for a column `hist` can't draw.
+    pub fn unsupported(&self, data_type: impl std::fmt::Display) -> ReplError {
+        ReplError::new(
+            ErrorKind::Planning,
+            format!("Can't draw a histogram of {} ({data_type})", self.column),
+        )
+        .with_hint("hist needs a numeric, date or timestamp column, `top` counts the others")
+    }
+}
+
+impl CmdExecutor for HistOps {
+    async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let hist = backend.hist(&self).await?;
+        let buckets = match hist.values {
+            HistValues::Bins { min, max, counts } => bins(min, max, &counts),
+            HistValues::Days(days) => periods(&days, self.bins()),
+        };
+        Ok(render(&buckets, hist.nulls, hist.non_finite, self.log))
+    }
+}
+
+/// Label the bins with their ranges.
+fn bins(min: f64, max: f64, counts: &[u64]) -> Vec<(String, u64)> {
+    let width = (max - min) / counts.len() as f64;
+    counts
+        .iter()
+        .enumerate()
+        .map(|(i, count)| {
+            let (from, to) = (min + width * i as f64, min + width * (i + 1) as f64);
+            let close = if i + 1 == counts.len() { ']' } else { ')' };
+            let label = format!("[{}, {}{close}", format_edge(from), format_edge(to));
+            (label, *count)
+        })
+        .collect()
+}
+
+/// Bucket the day counts by the finest period that fits in `bins`, empty periods included.
+fn periods(days: &[(i32, u64)], bins: usize) -> Vec<(String, u64)> {
+    let dates: BTreeMap<_, _> = days
+        .iter()
+        .filter_map(|(day, count)| {
+            let date = NaiveDate::from_num_days_from_ce_opt(day + EPOCH_DAYS_FROM_CE)?;
+            Some((date, *count))
+        })
+        .collect();
+    let (Some(first), Some(last)) = (dates.keys().next(), dates.keys().next_back()) else {
+        return vec![];
+    };
+
+    let starts = |period: Period| {
+        let mut starts = vec![period.start(*first)];
+        while let Some(next) = starts.last().and_then(|s| period.next(*s)) {
+            if next > *last {
+                break;
+            }
+            starts.push(next);
+        }
+        starts
+    };
+    let (period, starts) = [Period::Day, Period::Week, Period::Month]
+        .into_iter()
+        .map(|p| (p, starts(p)))
+        .find(|(_, starts)| starts.len() <= bins)
+        .unwrap_or_else(|| (Period::Year, starts(Period::Year)));
+
+    let mut counts: BTreeMap<_, u64> = starts.iter().map(|s| (*s, 0)).collect();
+    for (date, count) in dates {
+        *counts.entry(period.start(date)).or_default() += count;
+    }
+    counts
+        .into_iter()
+        .map(|(start, count)| (period.label(start), count))
+        .collect()
+}
+
+impl Period {
+    fn start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => date,
+            Period::Week => date - TimeDelta::days(date.weekday().num_days_from_monday() as i64),
+            Period::Month => date.with_day(1).expect("every month has a first day"),
+            Period::Year => date.with_ordinal(1).expect("every year has a first day"),
+        }
+    }
+
+    fn next(self, start: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Period::Day => start.succ_opt(),
+            Period::Week => start.checked_add_signed(TimeDelta::days(7)),
+            Period::Month => start.checked_add_months(Months::new(1)),
+            Period::Year => start.checked_add_months(Months::new(12)),
+        }
+    }
+
+    fn label(self, start: NaiveDate) -> String {
+        match self {
+            Period::Day => start.format("%Y-%m-%d").to_string(),
+            Period::Week => start.format("week of %Y-%m-%d").to_string(),
+            Period::Month => start.format("%Y-%m").to_string(),
+            Period::Year => start.format("%Y").to_string(),
+        }
+    }
+}
+
+/// A bin edge with at most 4 decimals, and none for whole numbers.
+fn format_edge(x: f64) -> String {
+    let s = format!("{x:.4}");
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Draw one bar per bucket, the longest one `BAR_WIDTH` wide, with eighths of a block for the
+/// remainders.
+fn render(buckets: &[(String, u64)], nulls: u64, non_finite: u64, log: bool) -> String {
+    const PARTIAL: [&str; 8] = ["", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+
+    let mut out = String::new();
+    if buckets.is_empty() {
+        out.push_str("No values to draw");
+    }
+    let scale = |count: u64| match log {
+        true => (count as f64).ln_1p(),
+        false => count as f64,
+    };
+    let max = buckets.iter().map(|(_, c)| scale(*c)).fold(0.0, f64::max);
+    let label_width = buckets.iter().map(|(l, _)| l.chars().count()).max();
+    let label_width = label_width.unwrap_or_default();
+    for (label, count) in buckets {
+        let eighths = match max > 0.0 {
+            true => (scale(*count) / max * (BAR_WIDTH * 8) as f64).round() as usize,
+            false => 0,
+        };
+        let bar = "█".repeat(eighths / 8) + PARTIAL[eighths % 8];
+        // writing to a String can't fail
+        let _ = writeln!(out, "{label:>label_width$} │{bar:<BAR_WIDTH$} {count}");
+    }
+    if nulls > 0 {
+        let _ = writeln!(out, "{nulls} nulls not shown");
+    }
+    if non_finite > 0 {
+        let _ = writeln!(out, "{non_finite} NaN or infinite values not shown");
+    }
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(date: &str) -> i32 {
+        let date: NaiveDate = date.parse().unwrap();
+        date.num_days_from_ce() - EPOCH_DAYS_FROM_CE
+    }
+
+    #[test]
+    fn periods_should_pick_the_finest_that_fits() {
+        let days = vec![(day("2024-01-01"), 2), (day("2024-01-03"), 1)];
+        let buckets = periods(&days, 20);
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[1], ("2024-01-02".to_string(), 0));
+
+        let days = vec![(day("2024-01-01"), 2), (day("2024-03-31"), 1)];
+        let buckets = periods(&days, 5);
+        assert_eq!(
+            buckets,
+            vec![
+                ("2024-01".to_string(), 2),
+                ("2024-02".to_string(), 0),
+                ("2024-03".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn render_should_scale_bars() {
+        let buckets = bins(0.0, 1.0, &[4, 2]);
+        assert_eq!(buckets[1].0, "[0.5, 1]");
+        let out = render(&buckets, 1, 2, false);
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].contains(&"█".repeat(BAR_WIDTH)));
+        assert!(lines[1].contains(&format!("{} ", "█".repeat(BAR_WIDTH / 2))));
+        assert_eq!(lines[2], "1 nulls not shown");
+        assert_eq!(lines[3], "2 NaN or infinite values not shown");
+    }
+}
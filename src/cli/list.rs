@@ -1,32 +1,38 @@
-use super::ReplResult;
-use crate::{CmdExecutor, ReplContext, ReplDisplay, ReplMsg};
-use clap::ArgMatches;
+use crate::{format::OutputFormat, pager, CmdExecutor, ErrorKind, ReplError};
 use clap::Parser;
 
 #[derive(Debug, Parser)]
-pub struct ListOps;
+pub struct ListOps {
+    #[arg(
+        long,
+        help = "Only show the sheets of the workbooks connected without `--sheet`. The table format shows them after the datasets"
+    )]
+    pub sheets: bool,
 
-pub fn list(_args: ArgMatches, context: &mut ReplContext) -> ReplResult {
-    let ret = ReplMsg::new(ListOps::new());
-
-    Ok(context.send(ret.0, ret.1))
+    #[arg(long, value_enum, help = "Output format, overriding `set format`")]
+    pub format: Option<OutputFormat>,
 }
 
 impl ListOps {
-    pub fn new() -> Self {
-        Self
-    }
-}
-
-impl Default for ListOps {
-    fn default() -> Self {
-        Self::new()
+    pub fn new(sheets: bool, format: Option<OutputFormat>) -> Self {
+        Self { sheets, format }
     }
 }
 
 impl CmdExecutor for ListOps {
     async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let sheets = backend.sheets().await?;
+        if self.sheets {
+            let sheets = sheets.ok_or_else(|| {
+                ReplError::new(
+                    ErrorKind::Planning,
+                    "No workbook is connected without `--sheet`",
+                )
+            })?;
+            return pager::display(backend, sheets, self.format).await;
+        }
         let df = backend.list().await?;
-        df.display().await
+        let ret = pager::display(backend, df, self.format).await?;
+        pager::append(backend, ret, sheets, self.format).await
     }
 }
@@ -1,15 +1,25 @@
 pub mod connect;
+pub mod corr;
+pub mod create;
+pub mod crosstab;
 pub mod describe;
+pub mod drop;
+pub mod explain;
+pub mod export;
 pub mod head;
+pub mod hist;
 pub mod list;
+pub mod next;
 pub mod schema;
+pub mod session;
+pub mod set;
+pub mod source;
 pub mod sql;
+pub mod top;
 
 use clap::Parser;
 use enum_dispatch::enum_dispatch;
 
-pub type ReplResult = Result<Option<String>, reedline_repl_rs::Error>;
-
 #[derive(Parser, Debug)]
 #[enum_dispatch(CmdExecutor)]
 pub enum ReplCommand {
@@ -25,12 +35,77 @@ pub enum ReplCommand {
     #[command(name = "schema", about = "Show schema of a dataset")]
     Schema(schema::SchemaOps),
 
-    #[command(name = "describe", about = "Describe a dataset")]
+    #[command(name = "describe", about = "Describe a dataset or a query result")]
     Describe(describe::DescribeOps),
 
     #[command(name = "head", about = "Show first few rows of a dataset")]
     Head(head::HeadOps),
 
+    #[command(
+        name = "top",
+        about = "Show the most frequent values of a column with their share"
+    )]
+    Top(top::TopOps),
+
+    #[command(
+        name = "hist",
+        about = "Draw the distribution of a numeric or temporal column"
+    )]
+    Hist(hist::HistOps),
+
+    #[command(
+        name = "corr",
+        about = "Show the correlations between the numeric and temporal columns"
+    )]
+    Corr(corr::CorrOps),
+
+    #[command(
+        name = "crosstab",
+        about = "Count the rows of each pair of values of two columns"
+    )]
+    Crosstab(crosstab::CrosstabOps),
+
     #[command(name = "sql", about = "Query a dataset using given SQL")]
     Sql(sql::SqlOps),
+
+    #[command(name = "explain", about = "Show how a SQL query is planned and run")]
+    Explain(explain::ExplainOps),
+
+    #[command(
+        name = "export",
+        about = "Export a dataset or a query result to a file"
+    )]
+    Export(export::ExportOps),
+
+    #[command(name = "create", about = "Create a dataset from a SQL query")]
+    Create(create::CreateOps),
+
+    #[command(name = "drop", about = "Drop a registered dataset")]
+    Drop(drop::DropOps),
+
+    #[command(
+        name = "session",
+        about = "Save, load or clear the registered datasets"
+    )]
+    Session(session::SessionOps),
+
+    #[command(name = "source", about = "Run the commands in a script file")]
+    Source(source::SourceOps),
+
+    #[command(name = "set", about = "Change a setting of the REPL session")]
+    Set(set::SetOps),
+
+    #[command(name = "next", about = "Show the next page of the last result")]
+    Next(next::NextOps),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn repl_command_should_be_valid() {
+        ReplCommand::command().debug_assert();
+    }
 }
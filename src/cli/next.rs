@@ -0,0 +1,19 @@
+use clap::Parser;
+
+use crate::{pager, CmdExecutor, ErrorKind, ReplError};
+
+#[derive(Parser, Debug)]
+pub struct NextOps;
+
+impl CmdExecutor for NextOps {
+    async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let Some(pending) = backend.pending().take() else {
+            let mut err = ReplError::new(ErrorKind::Execution, "No more rows to show");
+            if !backend.settings().pager {
+                err = err.with_hint("turn the pager on with `set pager on`");
+            }
+            return Err(err.into());
+        };
+        pager::show_page(backend, pending.rows, pending.format).await
+    }
+}
@@ -1,35 +1,47 @@
-use clap::{ArgMatches, Parser};
+use clap::Parser;
 
-use crate::{CmdExecutor, ReplContext, ReplDisplay, ReplMsg};
-
-use super::ReplResult;
+use crate::{format::OutputFormat, pager, CmdExecutor, ErrorKind, ReplError};
 
 #[derive(Parser, Debug)]
 pub struct SchemaOps {
     #[arg(help = "The name of the dataset")]
     pub name: String,
-}
-
-pub fn schema(args: ArgMatches, context: &mut ReplContext) -> ReplResult {
-    let name = args
-        .get_one::<String>("name")
-        .expect("Name is required")
-        .to_owned();
 
-    let ret = ReplMsg::new(SchemaOps::new(name));
+    #[arg(
+        long,
+        help = "Only show how the file is stored, e.g. the stripes of an ORC file. The table format shows it after the columns"
+    )]
+    pub layout: bool,
 
-    Ok(context.send(ret.0, ret.1))
+    #[arg(long, value_enum, help = "Output format, overriding `set format`")]
+    pub format: Option<OutputFormat>,
 }
 
 impl SchemaOps {
-    pub fn new(name: String) -> Self {
-        Self { name }
+    pub fn new(name: String, layout: bool, format: Option<OutputFormat>) -> Self {
+        Self {
+            name,
+            layout,
+            format,
+        }
     }
 }
 
 impl CmdExecutor for SchemaOps {
     async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let layout = backend.layout(&self.name).await?;
+        if self.layout {
+            let layout = layout.ok_or_else(|| {
+                ReplError::new(
+                    ErrorKind::Planning,
+                    format!("{} has no layout to show", self.name),
+                )
+                .with_hint("only ORC files have one, run `schema` without `--layout`")
+            })?;
+            return pager::display(backend, layout, self.format).await;
+        }
         let df = backend.schema(&self.name).await?;
-        df.display().await
+        let ret = pager::display(backend, df, self.format).await?;
+        pager::append(backend, ret, layout, self.format).await
     }
 }
@@ -0,0 +1,73 @@
+use clap::{Parser, Subcommand};
+
+use crate::{
+    catalog::{self, SessionCatalog},
+    CmdExecutor,
+};
+
+#[derive(Parser, Debug)]
+pub struct SessionOps {
+    #[command(subcommand)]
+    pub action: SessionAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SessionAction {
+    #[command(about = "Save the registered datasets to a session file")]
+    Save {
+        #[arg(help = "The session file")]
+        file: String,
+    },
+
+    #[command(about = "Register the datasets of a session file")]
+    Load {
+        #[arg(help = "The session file")]
+        file: String,
+    },
+
+    #[command(about = "Drop all registered datasets, or delete a session file")]
+    Clear {
+        #[arg(help = "The session file to delete")]
+        file: Option<String>,
+    },
+}
+
+impl CmdExecutor for SessionOps {
+    async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        match self.action {
+            SessionAction::Save { file } => {
+                backend.catalog().save(&file)?;
+                let count = backend.catalog().names().len();
+                Ok(format!("Saved {count} datasets to: {file}"))
+            }
+            SessionAction::Load { file } => {
+                let entries = SessionCatalog::read(&file)?;
+                let (restored, errors) = catalog::restore(backend, entries).await;
+                let mut ret = format!("Loaded {restored} datasets from: {file}");
+                for e in errors {
+                    ret.push_str(&format!("\nFailed to restore {e}"));
+                }
+                Ok(ret)
+            }
+            SessionAction::Clear { file: Some(file) } => {
+                std::fs::remove_file(&file)?;
+                Ok(format!("Deleted session: {file}"))
+            }
+            SessionAction::Clear { file: None } => {
+                let names: Vec<_> = backend
+                    .catalog()
+                    .names()
+                    .into_iter()
+                    .rev()
+                    .map(String::from)
+                    .collect();
+                for name in &names {
+                    // the datasets that failed to restore aren't registered
+                    let _ = backend.drop(name).await;
+                }
+                backend.catalog().clear();
+                Ok(format!("Dropped {} datasets", names.len()))
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use clap::{builder::BoolishValueParser, ArgAction, Parser, Subcommand};
+
+use crate::{format::OutputFormat, CmdExecutor};
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+pub struct SetOps {
+    #[command(subcommand)]
+    pub option: SetOption,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SetOption {
+    #[command(about = "Set the default output format")]
+    Format {
+        #[arg(value_enum, help = "The output format")]
+        format: OutputFormat,
+    },
+
+    #[command(about = "Set how many rows of a result are shown, 0 for all of them")]
+    MaxRows {
+        #[arg(help = "The number of rows")]
+        rows: usize,
+    },
+
+    #[command(about = "Turn paging through long results with `next` on or off")]
+    Pager {
+        #[arg(action = ArgAction::Set, value_parser = BoolishValueParser::new(), help = "on or off")]
+        enabled: bool,
+    },
+
+    #[command(about = "Cancel commands running longer than this, 0 to never cancel them")]
+    Timeout {
+        #[arg(help = "The timeout in seconds")]
+        secs: u64,
+    },
+}
+
+impl CmdExecutor for SetOps {
+    async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        match self.option {
+            SetOption::Format { format } => {
+                backend.settings().format = format;
+                Ok(format!("Output format: {format}"))
+            }
+            SetOption::MaxRows { rows } => {
+                backend.settings().max_rows = rows;
+                Ok(format!("Max rows: {rows}"))
+            }
+            SetOption::Pager { enabled } => {
+                backend.settings().pager = enabled;
+                // rows kept for `next` would otherwise linger after turning the pager off
+                if !enabled {
+                    backend.pending().take();
+                }
+                Ok(format!("Pager: {}", if enabled { "on" } else { "off" }))
+            }
+            SetOption::Timeout { secs } => {
+                backend.settings().timeout = (secs > 0).then(|| Duration::from_secs(secs));
+                match secs {
+                    0 => Ok("Timeout: off".to_string()),
+                    _ => Ok(format!("Timeout: {secs}s")),
+                }
+            }
+        }
+    }
+}
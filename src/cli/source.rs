@@ -0,0 +1,21 @@
+use clap::Parser;
+
+use crate::CmdExecutor;
+
+#[derive(Parser, Debug)]
+pub struct SourceOps {
+    #[arg(help = "The script file, one command per line")]
+    pub file: String,
+}
+
+impl SourceOps {
+    pub fn new(file: String) -> Self {
+        Self { file }
+    }
+}
+
+impl CmdExecutor for SourceOps {
+    async fn execute<T: crate::Backend>(self, _backend: &mut T) -> anyhow::Result<String> {
+        anyhow::bail!("source is run by the REPL, not the backend")
+    }
+}
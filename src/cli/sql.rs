@@ -1,35 +1,55 @@
-use clap::{ArgMatches, Parser};
+use clap::Parser;
 
-use crate::{CmdExecutor, ReplContext, ReplDisplay, ReplMsg};
+use datafusion::sql::sqlparser::{
+    ast::Statement, dialect::GenericDialect, parser::Parser as SqlParser,
+};
 
-use super::ReplResult;
+use crate::{format::OutputFormat, pager, CmdExecutor};
 
 #[derive(Debug, Parser)]
 pub struct SqlOps {
     #[arg(help = "SQL query to execute")]
     pub query: String,
-}
-
-pub fn head(args: ArgMatches, context: &mut ReplContext) -> ReplResult {
-    let query = args
-        .get_one::<String>("query")
-        .expect("Query is required")
-        .to_owned();
 
-    let ret = ReplMsg::new(SqlOps::new(query));
-
-    Ok(context.send(ret.0, ret.1))
+    #[arg(long, value_enum, help = "Output format, overriding `set format`")]
+    pub format: Option<OutputFormat>,
 }
 
 impl SqlOps {
-    pub fn new(query: String) -> Self {
-        Self { query }
+    pub fn new(query: String, format: Option<OutputFormat>) -> Self {
+        Self { query, format }
     }
 }
 
 impl CmdExecutor for SqlOps {
     async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
         let df = backend.sql(&self.query).await?;
-        df.display().await
+        // the plan is kept rather than the query, so describing it doesn't run a statement again
+        *backend.last_result() = is_query(&self.query).then(|| df.clone());
+        pager::display(backend, df, self.format).await
+    }
+}
+
+/// Whether `sql` is a query, rather than a statement run for its effect like an `insert` or a
+/// `create view`. Both engines parse SQL with the generic dialect.
+pub(crate) fn is_query(sql: &str) -> bool {
+    matches!(
+        SqlParser::parse_sql(&GenericDialect {}, sql).as_deref(),
+        Ok([Statement::Query(_)])
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_query_should_skip_statements() {
+        assert!(is_query("with t as (select 1) select * from t"));
+        assert!(is_query("values (1), (2)"));
+        assert!(!is_query("insert into t select * from s"));
+        assert!(!is_query("create view v as select * from t"));
+        assert!(!is_query("drop table t"));
+        assert!(!is_query("select 1; select 2"));
     }
 }
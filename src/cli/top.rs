@@ -0,0 +1,68 @@
+use clap::Parser;
+
+use crate::{format::OutputFormat, pager, CmdExecutor, ErrorKind, ReplError};
+
+#[derive(Parser, Debug)]
+pub struct TopOps {
+    #[arg(help = "The name of the dataset")]
+    pub name: String,
+
+    #[arg(help = "The column to count the values of, lists are counted by element")]
+    pub column: String,
+
+    #[arg(short, help = "Number of values to show")]
+    pub n: Option<usize>,
+
+    #[arg(long, help = "Show the share of each value instead of its count")]
+    pub normalize: bool,
+
+    #[arg(long, help = "Count nulls as a value")]
+    pub include_null: bool,
+
+    #[arg(long, value_enum, help = "Output format, overriding `set format`")]
+    pub format: Option<OutputFormat>,
+}
+
+impl TopOps {
+    pub fn new(
+        name: String,
+        column: String,
+        n: Option<usize>,
+        normalize: bool,
+        include_null: bool,
+        format: Option<OutputFormat>,
+    ) -> Self {
+        Self {
+            name,
+            column,
+            n,
+            normalize,
+            include_null,
+            format,
+        }
+    }
+
+    /// How many values to show, 10 by default.
+    pub fn limit(&self) -> usize {
+        self.n.unwrap_or(10)
+    }
+
+    /// Make sure the column is one of `available`.
+    pub fn check_column(&self, available: &[&str]) -> Result<(), ReplError> {
+        match available.contains(&self.column.as_str()) {
+            true => Ok(()),
+            false => Err(ReplError::new(
+                ErrorKind::Planning,
+                format!("Column not found: {}", self.column),
+            )
+            .with_hint(format!("run `schema {}` to see the columns", self.name))),
+        }
+    }
+}
+
+impl CmdExecutor for TopOps {
+    async fn execute<T: crate::Backend>(self, backend: &mut T) -> anyhow::Result<String> {
+        let df = backend.top(&self).await?;
+        pager::display(backend, df, self.format).await
+    }
+}
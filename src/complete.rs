@@ -0,0 +1,262 @@
+use clap::{Command, CommandFactory};
+use reedline::{Completer, Span, Suggestion};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+};
+
+use crate::ReplCommand;
+
+/// Column names of every registered dataset, refreshed by the backend after each command.
+pub type Schemas = Arc<RwLock<BTreeMap<String, Vec<String>>>>;
+
+#[rustfmt::skip]
+pub const SQL_KEYWORDS: &[&str] = &[
+    "ALL", "AND", "AS", "ASC", "AVG", "BETWEEN", "BY", "CASE", "CAST", "COUNT", "CROSS", "DESC",
+    "DISTINCT", "ELSE", "END", "EXCEPT", "EXISTS", "FALSE", "FROM", "FULL", "GROUP", "HAVING",
+    "ILIKE", "IN", "INNER", "INTERSECT", "IS", "JOIN", "LEFT", "LIKE", "LIMIT", "MAX", "MIN",
+    "NOT", "NULL", "OFFSET", "ON", "OR", "ORDER", "OUTER", "OVER", "PARTITION", "RIGHT", "SELECT",
+    "SUM", "THEN", "TRUE", "UNION", "USING", "VALUES", "WHEN", "WHERE", "WITH",
+];
+
+/// Commands whose first argument is a dataset name.
+const DATASET_COMMANDS: &[&str] = &[
+    "schema", "describe", "head", "drop", "export", "top", "hist", "corr", "crosstab",
+];
+/// Commands whose arguments are a SQL query.
+pub const SQL_COMMANDS: &[&str] = &["sql", "create", "explain"];
+
+/// Completes command names and options, dataset names, and table names, column names and
+/// keywords inside SQL queries.
+pub struct ReplCompleter {
+    command: Command,
+    schemas: Schemas,
+}
+
+impl ReplCompleter {
+    pub fn new(schemas: Schemas) -> Self {
+        Self {
+            command: ReplCommand::command(),
+            schemas,
+        }
+    }
+
+    fn complete_sql(&self, line: &str, pos: usize) -> Vec<Suggestion> {
+        let start = word_start(&line[..pos], |c| {
+            c.is_whitespace() || "\"'(),.;=<>+-*/".contains(c)
+        });
+        let word = &line[start..pos];
+        let span = Span::new(start, pos);
+        let schemas = self.schemas.read().expect("schemas lock poisoned");
+
+        // `table.col`: only the columns of that table
+        if line[..start].ends_with('.') {
+            let table = identifier_before(&line[..start - 1]);
+            return match schemas.get(table) {
+                Some(columns) => suggest(columns.iter(), word, span),
+                None => vec![],
+            };
+        }
+
+        // the columns of the tables the query mentions, or of all of them if it mentions none
+        let words: Vec<_> = line
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .collect();
+        let mentioned: Vec<_> = schemas
+            .keys()
+            .filter(|t| words.contains(&t.as_str()))
+            .collect();
+        let columns = schemas
+            .iter()
+            .filter(|(t, _)| mentioned.is_empty() || mentioned.contains(t))
+            .flat_map(|(_, columns)| columns);
+
+        let mut ret = suggest(schemas.keys().chain(columns), word, span);
+        // keywords follow the case the user started typing in
+        let lower = word.chars().next().is_some_and(|c| c.is_lowercase());
+        ret.extend(
+            SQL_KEYWORDS
+                .iter()
+                .filter(|k| !word.is_empty() && starts_with_ignore_case(k, word))
+                .map(|k| {
+                    suggestion(
+                        if lower {
+                            k.to_lowercase()
+                        } else {
+                            k.to_string()
+                        },
+                        span,
+                    )
+                }),
+        );
+        ret.dedup_by(|a, b| a.value == b.value);
+        ret
+    }
+
+    /// Options, subcommands and possible values of the innermost command on the line.
+    fn complete_args(&self, words: &[&str], word: &str, span: Span) -> Vec<Suggestion> {
+        let mut command = &self.command;
+        for w in words {
+            if let Some(sub) = command.find_subcommand(w) {
+                command = sub;
+            }
+        }
+
+        let mut values = Vec::new();
+        for arg in command.get_arguments().filter(|a| !a.is_global_set()) {
+            values.extend(
+                arg.get_possible_values()
+                    .iter()
+                    .map(|v| v.get_name().to_string()),
+            );
+            values.extend(arg.get_long().map(|l| format!("--{l}")));
+            values.extend(arg.get_short().map(|s| format!("-{s}")));
+        }
+        values.extend(
+            command
+                .get_subcommands()
+                .map(|s| s.get_name().to_string())
+                .filter(|s| s != "help"),
+        );
+        suggest(values.iter(), word, span)
+    }
+}
+
+impl Completer for ReplCompleter {
+    fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+        let start = word_start(&line[..pos], char::is_whitespace);
+        let word = &line[start..pos];
+        let span = Span::new(start, pos);
+        let words: Vec<_> = line[..start].split_whitespace().collect();
+
+        let Some(cmd) = words.first() else {
+            let commands = self.command.get_subcommands().map(|c| c.get_name());
+            return suggest(commands, word, span);
+        };
+
+        if word.starts_with('-') {
+            return self.complete_args(&words, word, span);
+        }
+        if SQL_COMMANDS.contains(cmd) {
+            return self.complete_sql(line, pos);
+        }
+        if DATASET_COMMANDS.contains(cmd) && words.len() == 1 {
+            let schemas = self.schemas.read().expect("schemas lock poisoned");
+            return suggest(schemas.keys(), word, span);
+        }
+        // `top <dataset> <column>`, `hist <dataset> <column>` and `crosstab <dataset> <row> <column>`
+        let column_args = match *cmd {
+            "top" | "hist" => 1,
+            "crosstab" => 2,
+            _ => 0,
+        };
+        if (2..2 + column_args).contains(&words.len()) {
+            let schemas = self.schemas.read().expect("schemas lock poisoned");
+            return match schemas.get(words[1]) {
+                Some(columns) => suggest(columns.iter(), word, span),
+                None => vec![],
+            };
+        }
+        self.complete_args(&words, word, span)
+    }
+}
+
+/// The identifier right before the end of `s`, e.g. the table of `table.`.
+fn identifier_before(s: &str) -> &str {
+    &s[word_start(s, |c| !(c.is_alphanumeric() || c == '_'))..]
+}
+
+/// Where the word at the end of `s` starts: after the last separator, which can take more than a
+/// byte, e.g. a no-break space.
+fn word_start(s: &str, separator: impl Fn(char) -> bool) -> usize {
+    s.char_indices()
+        .rev()
+        .find(|(_, c)| separator(*c))
+        .map_or(0, |(i, c)| i + c.len_utf8())
+}
+
+fn starts_with_ignore_case(s: &str, prefix: &str) -> bool {
+    s.len() >= prefix.len()
+        && s.is_char_boundary(prefix.len())
+        && s[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+fn suggest<S: AsRef<str>>(
+    candidates: impl Iterator<Item = S>,
+    word: &str,
+    span: Span,
+) -> Vec<Suggestion> {
+    candidates
+        .filter(|c| starts_with_ignore_case(c.as_ref(), word))
+        .map(|c| suggestion(c.as_ref().to_string(), span))
+        .collect()
+}
+
+fn suggestion(value: String, span: Span) -> Suggestion {
+    Suggestion {
+        value,
+        description: None,
+        style: None,
+        extra: None,
+        span,
+        append_whitespace: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completer() -> ReplCompleter {
+        let schemas = BTreeMap::from([
+            (
+                "users".to_string(),
+                vec!["email".to_string(), "name".to_string()],
+            ),
+            ("orders".to_string(), vec!["id".to_string()]),
+        ]);
+        ReplCompleter::new(Arc::new(RwLock::new(schemas)))
+    }
+
+    fn values(completer: &mut ReplCompleter, line: &str) -> Vec<String> {
+        completer
+            .complete(line, line.len())
+            .into_iter()
+            .map(|s| s.value)
+            .collect()
+    }
+
+    #[test]
+    fn completer_should_work() {
+        let mut c = completer();
+        assert_eq!(values(&mut c, "desc"), vec!["describe"]);
+        assert_eq!(values(&mut c, "head u"), vec!["users"]);
+        assert_eq!(values(&mut c, "head users --f"), vec!["--format"]);
+        assert_eq!(values(&mut c, "top users e"), vec!["email"]);
+        assert_eq!(values(&mut c, "hist users e"), vec!["email"]);
+        assert_eq!(values(&mut c, "crosstab users name e"), vec!["email"]);
+        assert_eq!(values(&mut c, "set format ma"), vec!["markdown"]);
+        assert_eq!(values(&mut c, r#"sql "select em"#), vec!["email"]);
+        assert_eq!(
+            values(&mut c, r#"sql "select * from users whe"#),
+            vec!["when", "where"]
+        );
+        assert_eq!(values(&mut c, r#"sql "select users.n"#), vec!["name"]);
+        assert_eq!(
+            values(&mut c, r#"sql "select count(i"#),
+            vec!["id", "ilike", "in", "inner", "intersect", "is"]
+        );
+        assert_eq!(
+            values(&mut c, r#"sql "SELECT * FROM users W"#),
+            vec!["WHEN", "WHERE", "WITH"]
+        );
+    }
+
+    #[test]
+    fn completer_should_handle_wide_whitespace() {
+        let mut c = completer();
+        assert_eq!(values(&mut c, "head\u{3000}u"), vec!["users"]);
+        assert_eq!(values(&mut c, "head users\u{a0}--f"), vec!["--format"]);
+        assert_eq!(values(&mut c, "sql \"select\u{3000}em"), vec!["email"]);
+    }
+}
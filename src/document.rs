@@ -0,0 +1,129 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+};
+
+use anyhow::bail;
+use serde_json::Value;
+
+use crate::cli::connect::JsonOps;
+
+/// Whether a JSON file is a single document holding the records, rather than one record after
+/// another as in NDJSON. It's a document if the records are at a `--path`, if it's an array, or
+/// if its first object spans several lines, e.g. it's pretty-printed: NDJSON has one a line.
+pub fn is_document(opts: &JsonOps) -> anyhow::Result<bool> {
+    if opts.path.is_some() {
+        return Ok(true);
+    }
+    let reader = opts
+        .file
+        .compression
+        .convert_read(File::open(&opts.file.filename)?)?;
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    while line.trim().is_empty() {
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(false);
+        }
+    }
+    let line = line.trim();
+    Ok(line.starts_with('[')
+        || line.starts_with('{') && serde_json::from_str::<Value>(line).is_err())
+}
+
+/// The records of a JSON document: the objects in the array at the path (the whole document by
+/// default). A lone object is a single record.
+pub fn read_records(opts: &JsonOps) -> anyhow::Result<Vec<Value>> {
+    let reader = opts
+        .file
+        .compression
+        .convert_read(File::open(&opts.file.filename)?)?;
+    let mut document: Value = serde_json::from_reader(BufReader::new(reader))?;
+    let path = opts.path.as_deref().unwrap_or_default();
+    if document.pointer(path).is_none() {
+        bail!(
+            "Nothing at {path} in {}{}",
+            opts.file.filename,
+            keys_hint(&document, path)
+        );
+    }
+    let records = match document.pointer_mut(path).map(Value::take) {
+        Some(Value::Array(records)) => records,
+        Some(record @ Value::Object(_)) => vec![record],
+        _ => bail!("{path} of {} isn't an array of records", opts.file.filename),
+    };
+    if records.iter().any(|r| !r.is_object()) {
+        bail!(
+            "{path} of {} has values that aren't records, only arrays of objects can be read",
+            opts.file.filename
+        );
+    }
+    Ok(records)
+}
+
+/// The keys of the deepest object on the path, to show where the path went wrong.
+fn keys_hint(document: &Value, path: &str) -> String {
+    let mut found = (String::new(), document);
+    for (i, _) in path.match_indices('/').skip(1) {
+        match document.pointer(&path[..i]) {
+            Some(value) => found = (path[..i].to_string(), value),
+            None => break,
+        }
+    }
+    match found {
+        (prefix, Value::Object(map)) => {
+            let keys: Vec<_> = map.keys().map(|k| k.as_str()).collect();
+            let prefix = if prefix.is_empty() {
+                "the document"
+            } else {
+                &prefix
+            };
+            format!(", {prefix} has {}", keys.join(", "))
+        }
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+
+    use super::*;
+    use crate::cli::connect::FileOps;
+
+    fn opts(filename: &str, path: Option<&str>) -> JsonOps {
+        JsonOps {
+            file: FileOps {
+                filename: filename.to_string(),
+                ext: "json".to_string(),
+                compression: FileCompressionType::UNCOMPRESSED,
+            },
+            path: path.map(|p| p.to_string()),
+        }
+    }
+
+    #[test]
+    fn is_document_should_tell_arrays_from_ndjson() -> anyhow::Result<()> {
+        assert!(is_document(&opts("assets/user.json", None))?);
+        assert!(!is_document(&opts("assets/users.ndjson", None))?);
+        assert!(is_document(&opts("assets/orders.json", None))?);
+        assert!(is_document(&opts(
+            "assets/orders.json",
+            Some("/data/items")
+        ))?);
+        Ok(())
+    }
+
+    #[test]
+    fn read_records_should_follow_the_path() -> anyhow::Result<()> {
+        let records = read_records(&opts("assets/orders.json", Some("/data/items")))?;
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1]["customer"]["name"], "Jane Smith");
+
+        let err = read_records(&opts("assets/orders.json", Some("/data/itemz"))).unwrap_err();
+        assert!(err.to_string().ends_with("/data has items"), "{err}");
+        let err = read_records(&opts("assets/orders.json", Some("/meta/total"))).unwrap_err();
+        assert!(err.to_string().contains("isn't an array"), "{err}");
+        Ok(())
+    }
+}
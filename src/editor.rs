@@ -0,0 +1,252 @@
+use anyhow::bail;
+use clap::CommandFactory;
+use datafusion::sql::sqlparser::{
+    dialect::GenericDialect,
+    tokenizer::{Token, Tokenizer},
+};
+use nu_ansi_term::{Color, Style};
+use reedline::{
+    default_emacs_keybindings, ColumnarMenu, DefaultHinter, DefaultPrompt, Emacs,
+    FileBackedHistory, Highlighter, HistoryItem, KeyCode, KeyModifiers, MenuBuilder, Prompt,
+    PromptEditMode, PromptHistorySearch, Reedline, ReedlineEvent, ReedlineMenu, Signal, StyledText,
+    ValidationResult, Validator,
+};
+use std::{borrow::Cow, env, fs, path::PathBuf, process::Command};
+
+use crate::{
+    complete::{ReplCompleter, SQL_COMMANDS, SQL_KEYWORDS},
+    script::{is_incomplete, query_start},
+    ErrorKind, ReplCommand, ReplContext, ReplError,
+};
+
+const COMPLETION_MENU: &str = "completion_menu";
+/// Typed at the end of the input, opens it in `$VISUAL`/`$EDITOR` like psql's `\e`.
+const EDIT_COMMAND: &str = "\\e";
+
+impl ReplContext {
+    /// Read commands from the terminal and run them, until Ctrl-D.
+    pub fn run_repl(&self, history_file: PathBuf, history_size: usize) -> anyhow::Result<()> {
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(
+            KeyModifiers::NONE,
+            KeyCode::Tab,
+            ReedlineEvent::Menu(COMPLETION_MENU.to_string()),
+        );
+        let menu = ColumnarMenu::default().with_name(COMPLETION_MENU);
+        let history = FileBackedHistory::with_file(history_size, history_file)?;
+        let hinter =
+            DefaultHinter::default().with_style(Style::new().italic().fg(Color::LightGray));
+
+        let mut editor = Reedline::create()
+            .with_edit_mode(Box::new(Emacs::new(keybindings)))
+            .with_completer(Box::new(ReplCompleter::new(self.schemas.clone())))
+            .with_menu(ReedlineMenu::EngineCompleter(Box::new(menu)))
+            .with_quick_completions(true)
+            .with_hinter(Box::new(hinter))
+            .with_highlighter(Box::new(ReplHighlighter))
+            .with_validator(Box::new(ReplValidator))
+            .with_history(Box::new(history));
+        let prompt = ReplPrompt::default();
+        // what `\e` opens when there's nothing else typed
+        let mut last_statement = String::new();
+
+        loop {
+            match editor.read_line(&prompt)? {
+                Signal::Success(line) => {
+                    let mut line = line.trim().to_string();
+                    if let Some(buffer) = line.strip_suffix(EDIT_COMMAND) {
+                        let buffer = match buffer.trim() {
+                            "" => &last_statement,
+                            buffer => buffer,
+                        };
+                        line = match edit_statement(buffer) {
+                            Ok(line) => line,
+                            Err(e) => {
+                                eprintln!("{}", ReplError::new(ErrorKind::Io, e.to_string()));
+                                continue;
+                            }
+                        };
+                        if line.is_empty() {
+                            continue;
+                        }
+                        println!("{line}");
+                        editor
+                            .history_mut()
+                            .save(HistoryItem::from_command_line(&line))?;
+                    }
+                    if line.is_empty() {
+                        continue;
+                    }
+                    if line
+                        .split_whitespace()
+                        .next()
+                        .is_some_and(|cmd| SQL_COMMANDS.contains(&cmd))
+                    {
+                        last_statement = line.clone();
+                    }
+                    // errors are already printed, and the REPL keeps going
+                    let _ = self.run_line(&line);
+                }
+                Signal::CtrlC => continue,
+                Signal::CtrlD => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+struct ReplPrompt {
+    prefix: String,
+    default: DefaultPrompt,
+}
+
+impl Default for ReplPrompt {
+    fn default() -> Self {
+        Self {
+            prefix: Color::Green.bold().paint("repl> ").to_string(),
+            default: DefaultPrompt::default(),
+        }
+    }
+}
+
+impl Prompt for ReplPrompt {
+    fn render_prompt_left(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.prefix)
+    }
+
+    fn render_prompt_right(&self) -> Cow<'_, str> {
+        self.default.render_prompt_right()
+    }
+
+    fn render_prompt_indicator(&self, edit_mode: PromptEditMode) -> Cow<'_, str> {
+        self.default.render_prompt_indicator(edit_mode)
+    }
+
+    fn render_prompt_multiline_indicator(&self) -> Cow<'_, str> {
+        self.default.render_prompt_multiline_indicator()
+    }
+
+    fn render_prompt_history_search_indicator(
+        &self,
+        history_search: PromptHistorySearch,
+    ) -> Cow<'_, str> {
+        self.default
+            .render_prompt_history_search_indicator(history_search)
+    }
+}
+
+/// Keeps reading lines until a SQL statement ends with `;`.
+struct ReplValidator;
+
+impl Validator for ReplValidator {
+    fn validate(&self, line: &str) -> ValidationResult {
+        if is_incomplete(line) && !line.trim_end().ends_with(EDIT_COMMAND) {
+            ValidationResult::Incomplete
+        } else {
+            ValidationResult::Complete
+        }
+    }
+}
+
+/// Highlights the command name, and keywords, identifiers and literals in SQL queries.
+struct ReplHighlighter;
+
+impl Highlighter for ReplHighlighter {
+    fn highlight(&self, line: &str, _cursor: usize) -> StyledText {
+        let mut ret = StyledText::new();
+        let start = line.len() - line.trim_start().len();
+        let end = line[start..]
+            .find(char::is_whitespace)
+            .map_or(line.len(), |i| start + i);
+        let cmd = &line[start..end];
+        ret.push((Style::new(), line[..start].to_string()));
+        ret.push((Color::Green.bold(), cmd.to_string()));
+
+        // the query is either the rest of an unquoted statement, or the inside of the quotes
+        let query = if !SQL_COMMANDS.contains(&cmd) {
+            None
+        } else if let Some(i) = query_start(line) {
+            Some((i, line.len()))
+        } else if let Some(i) = line.find('"') {
+            let j = line[i + 1..].find('"').map_or(line.len(), |j| i + 1 + j);
+            Some((i + 1, j))
+        } else {
+            None
+        };
+        match query {
+            Some((from, to)) => {
+                ret.push((Style::new(), line[end..from].to_string()));
+                ret.buffer.extend(highlight_sql(&line[from..to]));
+                ret.push((Style::new(), line[to..].to_string()));
+            }
+            None => ret.push((Style::new(), line[end..].to_string())),
+        }
+        ret
+    }
+}
+
+fn highlight_sql(sql: &str) -> Vec<(Style, String)> {
+    let plain = || vec![(Style::new(), sql.to_string())];
+    // a query being typed often doesn't tokenize, e.g. in the middle of a string
+    let Ok(tokens) = Tokenizer::new(&GenericDialect {}, sql).tokenize() else {
+        return plain();
+    };
+    let styled: Vec<_> = tokens
+        .into_iter()
+        .map(|token| {
+            let style = match &token {
+                Token::Word(w)
+                    if w.quote_style.is_none()
+                        && SQL_KEYWORDS.contains(&w.value.to_uppercase().as_str()) =>
+                {
+                    Color::Blue.bold()
+                }
+                Token::Word(_) => Color::Cyan.normal(),
+                Token::Number(..)
+                | Token::SingleQuotedString(_)
+                | Token::NationalStringLiteral(_)
+                | Token::HexStringLiteral(_) => Color::Yellow.normal(),
+                _ => Style::new(),
+            };
+            (style, token.to_string())
+        })
+        .collect();
+
+    // tokens don't always print back as they were typed, e.g. escaped quotes
+    if styled.iter().map(|(_, s)| s.as_str()).collect::<String>() != sql {
+        return plain();
+    }
+    styled
+}
+
+/// Open `buffer` in `$VISUAL` or `$EDITOR`, and return the edited statement, ready to run.
+fn edit_statement(buffer: &str) -> anyhow::Result<String> {
+    let path = env::temp_dir().join(format!("taotie-{}.sql", std::process::id()));
+    fs::write(&path, buffer)?;
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = Command::new(program).args(words).arg(&path).status();
+    let content = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+    let status = status?;
+    if !status.success() {
+        bail!("`{editor}` exited with {status}");
+    }
+
+    let mut line = content?.trim().to_string();
+    if line.is_empty() {
+        return Ok(line);
+    }
+    // a bare query runs as `sql`
+    let cmd = line.split_whitespace().next().unwrap_or_default();
+    if ReplCommand::command().find_subcommand(cmd).is_none() {
+        line = format!("sql {line}");
+    }
+    if is_incomplete(&line) {
+        line.push(';');
+    }
+    Ok(line)
+}
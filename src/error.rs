@@ -0,0 +1,231 @@
+use datafusion::error::DataFusionError;
+use polars::prelude::PolarsError;
+use regex::Regex;
+use std::{error::Error, fmt, sync::LazyLock};
+
+// the ways the engines, and taotie itself, report a dataset that isn't registered
+static MISSING_DATASET_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"table '(?:[^'.]+\.)*([^'.]+)' not found|relation '([^']+)' was not found|Dataset not found: (\S+)",
+    )
+    .expect("valid regex")
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The command or the SQL couldn't be parsed.
+    Parse,
+    /// The query is well-formed but refers to something that doesn't exist or doesn't fit.
+    Planning,
+    /// A file or a connection couldn't be read or written.
+    Io,
+    /// Anything that went wrong while running the query.
+    Execution,
+    /// The command was interrupted with Ctrl-C.
+    Cancelled,
+    /// The command ran longer than the `set timeout` limit.
+    Timeout,
+}
+
+/// The error a command reports back to the REPL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl ReplError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// The process exit code for batch mode.
+    pub fn exit_code(&self) -> i32 {
+        match self.kind {
+            ErrorKind::Parse => 2,
+            ErrorKind::Planning => 3,
+            ErrorKind::Io => 4,
+            ErrorKind::Execution => 5,
+            // the codes shells and `timeout(1)` use
+            ErrorKind::Cancelled => 130,
+            ErrorKind::Timeout => 124,
+        }
+    }
+
+    /// Categorize an error returned by a command. `datasets` are the registered dataset names,
+    /// used to suggest one when the error is about a missing dataset.
+    pub fn from_anyhow(err: anyhow::Error, datasets: &[&str]) -> Self {
+        let mut ret = match err.downcast::<ReplError>() {
+            Ok(e) => e,
+            Err(err) => {
+                let kind = err
+                    .chain()
+                    .find_map(kind_of)
+                    .unwrap_or(ErrorKind::Execution);
+                // without the "Error during planning: " style prefixes, the kind says that
+                let message = match err.downcast_ref::<DataFusionError>().map(|e| e.find_root()) {
+                    Some(DataFusionError::SQL(e, _)) => e.to_string(),
+                    Some(e) => e.message().into_owned(),
+                    None => err.to_string(),
+                };
+                let message = message
+                    .strip_prefix("sql parser error: ")
+                    .unwrap_or(&message);
+                Self::new(kind, message)
+            }
+        };
+
+        if ret.hint.is_none() {
+            ret.hint = missing_dataset(&ret.message).map(|name| match closest(name, datasets) {
+                Some(found) => format!("did you mean `{found}`?"),
+                None => "run `list` to see the registered datasets".to_string(),
+            });
+        }
+        ret
+    }
+}
+
+fn kind_of(e: &(dyn Error + 'static)) -> Option<ErrorKind> {
+    if let Some(e) = e.downcast_ref::<DataFusionError>() {
+        return match e.find_root() {
+            DataFusionError::SQL(..) => Some(ErrorKind::Parse),
+            DataFusionError::Plan(_)
+            | DataFusionError::SchemaError(..)
+            | DataFusionError::NotImplemented(_)
+            | DataFusionError::Configuration(_) => Some(ErrorKind::Planning),
+            DataFusionError::IoError(_) | DataFusionError::ObjectStore(_) => Some(ErrorKind::Io),
+            // the wrapped error is the next one in the chain
+            DataFusionError::External(_) => None,
+            _ => Some(ErrorKind::Execution),
+        };
+    }
+    if let Some(e) = e.downcast_ref::<PolarsError>() {
+        return match e {
+            PolarsError::SQLSyntax(_) => Some(ErrorKind::Parse),
+            // polars reports sqlparser errors as interface errors
+            PolarsError::SQLInterface(msg) if msg.starts_with("sql parser error") => {
+                Some(ErrorKind::Parse)
+            }
+            PolarsError::SQLInterface(_)
+            | PolarsError::ColumnNotFound(_)
+            | PolarsError::SchemaFieldNotFound(_)
+            | PolarsError::StructFieldNotFound(_)
+            | PolarsError::SchemaMismatch(_)
+            | PolarsError::Duplicate(_)
+            | PolarsError::InvalidOperation(_) => Some(ErrorKind::Planning),
+            PolarsError::IO { .. } => Some(ErrorKind::Io),
+            _ => Some(ErrorKind::Execution),
+        };
+    }
+    if let Some(e) = e.downcast_ref::<tokio_postgres::Error>() {
+        // an error without a database error is a connection problem
+        return match e.as_db_error() {
+            Some(_) => Some(ErrorKind::Execution),
+            None => Some(ErrorKind::Io),
+        };
+    }
+    if e.is::<std::io::Error>() {
+        return Some(ErrorKind::Io);
+    }
+    None
+}
+
+fn missing_dataset(message: &str) -> Option<&str> {
+    let caps = MISSING_DATASET_RE.captures(message)?;
+    caps.iter().skip(1).flatten().next().map(|m| m.as_str())
+}
+
+/// The candidate closest to `name`, if it's close enough to be a typo.
+fn closest<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let name = name.to_lowercase();
+    candidates
+        .iter()
+        .map(|c| (edit_distance(&name, &c.to_lowercase()), *c))
+        .filter(|(d, c)| *d <= (c.chars().count() / 2).max(1))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                prev.min(cur).min(row[j]) + 1
+            };
+            prev = cur;
+        }
+    }
+    row[b.len()]
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            ErrorKind::Parse => "Parse",
+            ErrorKind::Planning => "Planning",
+            ErrorKind::Io => "IO",
+            ErrorKind::Execution => "Execution",
+            ErrorKind::Cancelled => "Cancelled",
+            ErrorKind::Timeout => "Timeout",
+        };
+        f.write_str(kind)
+    }
+}
+
+impl fmt::Display for ReplError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} error: {}", self.kind, self.message)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n  hint: {hint}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ReplError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_anyhow_should_suggest_closest_dataset() {
+        let err = DataFusionError::Plan("table 'datafusion.public.usres' not found".to_string());
+        let err = ReplError::from_anyhow(err.into(), &["users", "orders"]);
+        assert_eq!(err.kind, ErrorKind::Planning);
+        assert_eq!(err.message, "table 'datafusion.public.usres' not found");
+        assert_eq!(err.hint.as_deref(), Some("did you mean `users`?"));
+
+        let err = ReplError::from_anyhow(anyhow::anyhow!("Dataset not found: xyz"), &["users"]);
+        assert_eq!(err.kind, ErrorKind::Execution);
+        assert_eq!(
+            err.hint.as_deref(),
+            Some("run `list` to see the registered datasets")
+        );
+    }
+
+    #[test]
+    fn edit_distance_should_work() {
+        assert_eq!(edit_distance("users", "users"), 0);
+        assert_eq!(edit_distance("usres", "users"), 2);
+        assert_eq!(edit_distance("user", "users"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+}
@@ -0,0 +1,193 @@
+use arrow::{
+    array::RecordBatch,
+    json::{ArrayWriter, LineDelimitedWriter},
+    util::{
+        display::{ArrayFormatter, FormatOptions},
+        pretty::pretty_format_batches,
+    },
+};
+use clap::ValueEnum;
+use std::fmt::{self, Write};
+
+/// How query results are rendered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Csv,
+    Tsv,
+    Json,
+    #[value(name = "ndjson")]
+    NdJson,
+    Markdown,
+    Vertical,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("No format is skipped");
+        f.write_str(value.get_name())
+    }
+}
+
+pub fn format_batches(batches: &[RecordBatch], format: OutputFormat) -> anyhow::Result<String> {
+    let ret = match format {
+        OutputFormat::Table => pretty_format_batches(batches)?.to_string(),
+        OutputFormat::Csv => format_delimited(batches, ',')?,
+        OutputFormat::Tsv => format_delimited(batches, '\t')?,
+        OutputFormat::Json => {
+            let mut writer = ArrayWriter::new(Vec::new());
+            writer.write_batches(&batches.iter().collect::<Vec<_>>())?;
+            writer.finish()?;
+            let buf = writer.into_inner();
+            // an empty result writes nothing at all, rather than an empty array
+            if buf.is_empty() {
+                "[]".to_string()
+            } else {
+                String::from_utf8(buf)?
+            }
+        }
+        OutputFormat::NdJson => {
+            let mut writer = LineDelimitedWriter::new(Vec::new());
+            writer.write_batches(&batches.iter().collect::<Vec<_>>())?;
+            writer.finish()?;
+            String::from_utf8(writer.into_inner())?
+                .trim_end()
+                .to_string()
+        }
+        OutputFormat::Markdown => format_markdown(batches)?,
+        OutputFormat::Vertical => format_vertical(batches)?,
+    };
+    Ok(ret)
+}
+
+/// Column names of the batches, or an empty list if there are none.
+fn headers(batches: &[RecordBatch]) -> Vec<String> {
+    batches
+        .first()
+        .map(|b| {
+            b.schema()
+                .fields()
+                .iter()
+                .map(|f| f.name().clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Every row as a list of display strings. Nested values (lists, structs) are rendered the same
+/// way as in the table format, which arrow's csv writer refuses to do.
+fn rows(batches: &[RecordBatch]) -> anyhow::Result<Vec<Vec<String>>> {
+    let options = FormatOptions::default();
+    let mut rows = Vec::new();
+    for batch in batches {
+        let formatters = batch
+            .columns()
+            .iter()
+            .map(|c| ArrayFormatter::try_new(c.as_ref(), &options))
+            .collect::<Result<Vec<_>, _>>()?;
+        for i in 0..batch.num_rows() {
+            rows.push(formatters.iter().map(|f| f.value(i).to_string()).collect());
+        }
+    }
+    Ok(rows)
+}
+
+fn format_delimited(batches: &[RecordBatch], delimiter: char) -> anyhow::Result<String> {
+    let quote = |s: &str| {
+        if s.contains([delimiter, '"', '\n', '\r']) {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        } else {
+            s.to_string()
+        }
+    };
+    let sep = delimiter.to_string();
+
+    let mut lines = vec![headers(batches)
+        .iter()
+        .map(|h| quote(h))
+        .collect::<Vec<_>>()
+        .join(&sep)];
+    for row in rows(batches)? {
+        lines.push(row.iter().map(|v| quote(v)).collect::<Vec<_>>().join(&sep));
+    }
+    Ok(lines.join("\n"))
+}
+
+fn format_markdown(batches: &[RecordBatch]) -> anyhow::Result<String> {
+    let cell = |s: &str| s.replace('|', "\\|").replace('\n', "<br>");
+    let line = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+
+    let headers = headers(batches);
+    let mut lines = vec![
+        line(headers.iter().map(|h| cell(h)).collect()),
+        line(headers.iter().map(|_| "---".to_string()).collect()),
+    ];
+    for row in rows(batches)? {
+        lines.push(line(row.iter().map(|v| cell(v)).collect()));
+    }
+    Ok(lines.join("\n"))
+}
+
+/// One `name | value` block per row, which keeps wide rows readable.
+fn format_vertical(batches: &[RecordBatch]) -> anyhow::Result<String> {
+    let headers = headers(batches);
+    let width = headers.iter().map(|h| h.chars().count()).max().unwrap_or(0);
+
+    let mut out = String::new();
+    for (i, row) in rows(batches)?.into_iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        write!(out, "-[ RECORD {} ]-", i + 1)?;
+        for (name, value) in headers.iter().zip(row) {
+            write!(out, "\n{name:<width$} | {value}")?;
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int32Array, StringArray};
+    use std::sync::Arc;
+
+    fn batch() -> RecordBatch {
+        RecordBatch::try_from_iter(vec![
+            ("id", Arc::new(Int32Array::from(vec![1, 2])) as _),
+            (
+                "name",
+                Arc::new(StringArray::from(vec![Some("a,b"), None])) as _,
+            ),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn format_batches_should_work() -> anyhow::Result<()> {
+        let batches = [batch()];
+        assert_eq!(
+            format_batches(&batches, OutputFormat::Csv)?,
+            "id,name\n1,\"a,b\"\n2,"
+        );
+        assert_eq!(
+            format_batches(&batches, OutputFormat::Tsv)?,
+            "id\tname\n1\ta,b\n2\t"
+        );
+        assert_eq!(
+            format_batches(&batches, OutputFormat::NdJson)?,
+            "{\"id\":1,\"name\":\"a,b\"}\n{\"id\":2}"
+        );
+        assert_eq!(
+            format_batches(&batches, OutputFormat::Markdown)?,
+            "| id | name |\n| --- | --- |\n| 1 | a,b |\n| 2 |  |"
+        );
+        assert_eq!(
+            format_batches(&batches, OutputFormat::Vertical)?,
+            "-[ RECORD 1 ]-\nid   | 1\nname | a,b\n-[ RECORD 2 ]-\nid   | 2\nname | "
+        );
+        assert_eq!(format_batches(&[], OutputFormat::Json)?, "[]");
+        Ok(())
+    }
+}
@@ -1,18 +1,46 @@
 mod backend;
+mod catalog;
 mod cli;
+mod complete;
+mod document;
+mod editor;
+mod error;
+mod format;
+mod pager;
+mod script;
+mod settings;
+mod workbook;
 
-use backend::DataFusionBackend;
-use cli::{connect, describe, head, list, schema, sql};
+use backend::{DataFusionBackend, PolarsBackend};
+use catalog::SessionCatalog;
+use clap::ValueEnum;
+use cli::{
+    connect, corr, create, crosstab, describe, drop, explain, export, head, hist, list, next,
+    schema, session, set, source, sql, top,
+};
+use complete::Schemas;
 use crossbeam_channel as mpsc;
 use enum_dispatch::enum_dispatch;
-use reedline_repl_rs::CallBackMap;
+use format::OutputFormat;
+use settings::Settings;
 use std::{
+    collections::BTreeMap,
     ops::{Deref, DerefMut},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        LazyLock, Once,
+    },
     thread,
+    time::Duration,
 };
-use tokio::runtime::Runtime;
+use tokio::{runtime::Runtime, sync::Notify};
 
+// for the benchmarks
+#[doc(hidden)]
+pub use backend::DataFrameDescriber;
 pub use cli::ReplCommand;
+pub use error::{ErrorKind, ReplError};
 
 #[enum_dispatch]
 trait CmdExecutor {
@@ -23,77 +51,247 @@ trait Backend {
     type DataFrame: ReplDisplay;
     async fn connect(&mut self, opts: &cli::connect::ConnectOps) -> anyhow::Result<()>;
     async fn list(&self) -> anyhow::Result<Self::DataFrame>;
+    /// The sheets of the workbooks connected without `--sheet`, shown by `list`.
+    async fn sheets(&self) -> anyhow::Result<Option<Self::DataFrame>>;
     async fn schema(&self, name: &str) -> anyhow::Result<Self::DataFrame>;
-    async fn describe(&self, name: &str) -> anyhow::Result<Self::DataFrame>;
+    /// How a file dataset is stored, for the formats where it matters, e.g. the stripes of ORC.
+    async fn layout(&self, name: &str) -> anyhow::Result<Option<Self::DataFrame>>;
+    /// The statistics of a result.
+    async fn describe(
+        &self,
+        df: Self::DataFrame,
+        opts: &cli::describe::DescribeOps,
+    ) -> anyhow::Result<Self::DataFrame>;
     async fn head(&self, name: &str, size: usize) -> anyhow::Result<Self::DataFrame>;
+    /// The most frequent values of a column, with their count and share.
+    async fn top(&self, opts: &cli::top::TopOps) -> anyhow::Result<Self::DataFrame>;
+    /// The bin counts of a numeric column, or the daily counts of a temporal one.
+    async fn hist(&self, opts: &cli::hist::HistOps) -> anyhow::Result<cli::hist::Histogram>;
+    /// The correlation matrix of the numeric and temporal columns of a dataset.
+    async fn corr(&self, opts: &cli::corr::CorrOps) -> anyhow::Result<Self::DataFrame>;
+    /// The contingency table of two columns of a dataset.
+    async fn crosstab(&self, opts: &cli::crosstab::CrosstabOps) -> anyhow::Result<Self::DataFrame>;
     async fn sql(&self, sql: &str) -> anyhow::Result<Self::DataFrame>;
+    /// The plans of a query, and with `--analyze` the metrics of running it.
+    async fn explain(&self, opts: &cli::explain::ExplainOps) -> anyhow::Result<String>;
+    async fn export(&self, opts: &cli::export::ExportOps) -> anyhow::Result<usize>;
+    async fn create(&mut self, opts: &cli::create::CreateOps) -> anyhow::Result<()>;
+    async fn drop(&mut self, name: &str) -> anyhow::Result<()>;
+    /// Column names of every registered dataset, for tab completion.
+    async fn columns(&self) -> anyhow::Result<BTreeMap<String, Vec<String>>>;
+    fn catalog(&mut self) -> &mut SessionCatalog;
+    fn settings(&mut self) -> &mut Settings;
+    /// The rest of the last result, kept for `next` when the pager is on.
+    fn pending(&mut self) -> &mut Option<pager::Pending<Self>>;
+    /// The result of the last query run with `sql`, profiled by a bare `describe`.
+    fn last_result(&mut self) -> &mut Option<Self::DataFrame>;
+}
+
+trait ReplDisplay: Clone {
+    type Rows: ReplRows;
+    async fn rows(self) -> anyhow::Result<Self::Rows>;
+}
+
+/// A query result that is rendered a page at a time, so it never has to be held in memory.
+trait ReplRows {
+    /// Render at most `max_rows` more rows (all of them if it's 0), and tell whether any are left.
+    async fn next_page(
+        &mut self,
+        format: OutputFormat,
+        max_rows: usize,
+    ) -> anyhow::Result<(String, bool)>;
+    /// Count the rows not rendered yet, without keeping them.
+    async fn count_rest(self) -> anyhow::Result<usize>;
 }
 
-trait ReplDisplay {
-    async fn display(self) -> anyhow::Result<String>;
+/// The query engine backing a REPL session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Engine {
+    #[default]
+    #[value(name = "datafusion")]
+    DataFusion,
+    Polars,
 }
 
 pub struct ReplContext {
     pub tx: mpsc::Sender<ReplMsg>,
+    pub schemas: Schemas,
+    pub engine: Engine,
 }
 
+/// The output of a command, or why it failed.
+pub type ReplResponse = Result<String, ReplError>;
+
 pub struct ReplMsg {
     pub cmd: ReplCommand,
-    pub tx: oneshot::Sender<String>,
+    pub tx: oneshot::Sender<ReplResponse>,
 }
 
-pub type ReplCallbacks = CallBackMap<ReplContext, reedline_repl_rs::Error>;
+/// Woken by Ctrl-C. Only commands running at that moment are cancelled, nothing is remembered
+/// for later ones.
+static INTERRUPT: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// How many commands are waiting on `INTERRUPT`. With none, Ctrl-C exits the process.
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether Ctrl-C was pressed since the last command started. A query that never yields back to
+/// the runtime can't see `INTERRUPT`, so pressing it again exits.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
 
-pub fn get_callbacks() -> ReplCallbacks {
-    let mut callbacks: ReplCallbacks = CallBackMap::new();
-    callbacks.insert("connect".to_string(), cli::connect::connect);
-    callbacks.insert("list".to_string(), cli::list::list);
-    callbacks.insert("schema".to_string(), cli::schema::schema);
-    callbacks.insert("describe".to_string(), cli::describe::describe);
-    callbacks.insert("head".to_string(), cli::head::head);
-    callbacks.insert("sql".to_string(), cli::sql::head);
-    callbacks
+/// Counts a command as running until it's dropped.
+struct RunningGuard;
+
+impl RunningGuard {
+    fn new() -> Self {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        RUNNING.fetch_add(1, Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        RUNNING.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 impl ReplContext {
-    pub fn new() -> Self {
+    /// Start the backend thread. If a session file is given, the datasets saved in it are
+    /// restored and every later change to the registered datasets is saved back to it.
+    pub fn new(engine: Engine, session_file: Option<PathBuf>) -> Self {
         let (tx, rx) = mpsc::unbounded::<ReplMsg>();
-
-        let rt = Runtime::new().expect("Failed to create runtime");
-
-        let mut backend = DataFusionBackend::new();
+        let schemas = Schemas::default();
+        let backend_schemas = schemas.clone();
 
         thread::Builder::new()
             .name("ReplBackend".to_string())
             .spawn(move || {
-                while let Ok(msg) = rx.recv() {
-                    if let Err(e) = rt.block_on(async {
-                        let ret = msg.cmd.execute(&mut backend).await?;
-                        msg.tx.send(ret)?;
-                        Ok::<_, anyhow::Error>(())
-                    }) {
-                        eprintln!("Failed to process command: {}", e);
-                    }
+                let catalog = SessionCatalog::new(session_file);
+                let schemas = backend_schemas;
+                match engine {
+                    Engine::DataFusion => run_backend(DataFusionBackend::new(catalog), rx, schemas),
+                    Engine::Polars => run_backend(PolarsBackend::new(catalog), rx, schemas),
                 }
             })
             .unwrap();
 
-        Self { tx }
+        Self {
+            tx,
+            schemas,
+            engine,
+        }
     }
 
-    pub fn send(&self, msg: ReplMsg, rx: oneshot::Receiver<String>) -> Option<String> {
+    /// Run a command and wait for its response.
+    pub fn request(&self, msg: ReplMsg, rx: oneshot::Receiver<ReplResponse>) -> ReplResponse {
         if let Err(e) = self.tx.send(msg) {
             eprintln!("REPL Send Error: {}", e);
             std::process::exit(1);
         }
 
-        // if the oneshot receiver is dropped, return None, because the sender has been dropped
-        rx.recv().ok()
+        rx.recv().unwrap_or_else(|_| {
+            Err(ReplError::new(
+                ErrorKind::Execution,
+                "The backend stopped without a response",
+            ))
+        })
+    }
+}
+
+fn run_backend<T: Backend>(mut backend: T, rx: mpsc::Receiver<ReplMsg>, schemas: Schemas) {
+    let rt = Runtime::new().expect("Failed to create runtime");
+    // at the prompt the line editor gets Ctrl-C as a key press. Anywhere else, e.g. while a
+    // script is read from stdin or a session is restored, there's nothing to cancel and Ctrl-C
+    // exits like it would without a handler. There's one handler per process, shared by every
+    // backend.
+    static CTRLC_HANDLER: Once = Once::new();
+    CTRLC_HANDLER.call_once(|| {
+        let handler = || {
+            if RUNNING.load(Ordering::SeqCst) == 0 || INTERRUPTED.swap(true, Ordering::SeqCst) {
+                std::process::exit(130);
+            }
+            INTERRUPT.notify_waiters();
+        };
+        if let Err(e) = ctrlc::set_handler(handler) {
+            eprintln!("Failed to set the Ctrl-C handler: {}", e);
+        }
+    });
+
+    if let Some(file) = backend.catalog().autosave_file().map(|f| f.to_owned()) {
+        if file.exists() {
+            match SessionCatalog::read(&file) {
+                Ok(entries) => {
+                    let (restored, errors) = rt.block_on(catalog::restore(&mut backend, entries));
+                    println!("Restored {restored} datasets from: {}", file.display());
+                    for e in errors {
+                        eprintln!("Failed to restore {e}");
+                    }
+                }
+                Err(e) => eprintln!("Failed to read session {}: {}", file.display(), e),
+            }
+        }
+    }
+
+    // for tab completion, which runs on the REPL thread and can't ask the backend itself
+    let refresh_schemas = |backend: &T| {
+        if let Ok(columns) = rt.block_on(backend.columns()) {
+            *schemas.write().expect("schemas lock poisoned") = columns;
+        }
+    };
+    refresh_schemas(&backend);
+
+    while let Ok(ReplMsg { cmd, tx }) = rx.recv() {
+        let timeout = backend.settings().timeout;
+        let ret = rt
+            .block_on(execute(cmd, &mut backend, timeout))
+            .map_err(|e| ReplError::from_anyhow(e, &backend.catalog().names()));
+        refresh_schemas(&backend);
+        if let Err(e) = tx.send(ret) {
+            eprintln!("Failed to send result: {}", e);
+        }
+    }
+}
+
+/// Run a command until it finishes, Ctrl-C is pressed or the timeout expires. Dropping the
+/// command's future is what cancels the query.
+///
+/// Neither the interrupt nor the timeout go through tokio's signal and time drivers: a CPU-bound
+/// query can keep every runtime worker busy, and then the drivers are never polled.
+async fn execute<T: Backend>(
+    cmd: ReplCommand,
+    backend: &mut T,
+    timeout: Option<Duration>,
+) -> anyhow::Result<String> {
+    let _running = RunningGuard::new();
+    let interrupted = INTERRUPT.notified();
+
+    let (timeout_tx, timed_out) = tokio::sync::oneshot::channel();
+    // dropped when the command is done, which stops the timer thread
+    let (_done_tx, done_rx) = mpsc::bounded::<()>(0);
+    if let Some(timeout) = timeout {
+        thread::spawn(move || {
+            if done_rx.recv_timeout(timeout) == Err(mpsc::RecvTimeoutError::Timeout) {
+                let _ = timeout_tx.send(());
+            }
+        });
+    }
+
+    tokio::select! {
+        ret = cmd.execute(backend) => ret,
+        _ = interrupted => {
+            Err(ReplError::new(ErrorKind::Cancelled, "Command cancelled").into())
+        }
+        Ok(_) = timed_out => {
+            let secs = timeout.unwrap_or_default().as_secs();
+            let err = ReplError::new(ErrorKind::Timeout, format!("Command timed out after {secs}s"))
+                .with_hint("raise the limit with `set timeout <secs>`, 0 turns it off");
+            Err(err.into())
+        }
     }
 }
 
 impl ReplMsg {
-    pub fn new(cmd: impl Into<ReplCommand>) -> (Self, oneshot::Receiver<String>) {
+    pub fn new(cmd: impl Into<ReplCommand>) -> (Self, oneshot::Receiver<ReplResponse>) {
         let (tx, rx) = oneshot::channel();
         (
             Self {
@@ -107,7 +305,7 @@ impl ReplMsg {
 
 impl Default for ReplContext {
     fn default() -> Self {
-        Self::new()
+        Self::new(Engine::default(), None)
     }
 }
 
@@ -1,24 +1,76 @@
 use anyhow::Result;
-use reedline_repl_rs::Repl;
-use taotie::{get_callbacks, ReplCommand, ReplContext};
+use clap::Parser;
+use std::io::{self, IsTerminal, Read};
+use taotie::{Engine, ReplContext};
 
 const HISTORY_FILE: &str = ".taotie_history";
 const HISTORY_SIZE: usize = 1024;
+const SESSION_FILE: &str = ".taotie_session.json";
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about,
+    after_help = "In batch mode, the exit code tells the first failure apart: \
+                  2 parse, 3 planning, 4 IO, 5 execution error, 124 timeout, 130 Ctrl-C"
+)]
+struct Args {
+    #[arg(long, value_enum, default_value_t = Engine::DataFusion, help = "Query engine to use")]
+    engine: Engine,
+
+    #[arg(long, help = "Don't restore or save the registered datasets")]
+    no_session: bool,
+
+    #[arg(
+        short,
+        long = "command",
+        help = "Run the command and exit, can be given multiple times"
+    )]
+    commands: Vec<String>,
+
+    #[arg(short, long, help = "Run the commands in the script file and exit")]
+    file: Option<String>,
+
+    #[arg(
+        long,
+        help = "Keep running the remaining commands after a failure, unless it was Ctrl-C"
+    )]
+    continue_on_error: bool,
+}
 
 fn main() -> Result<()> {
-    let ctx = ReplContext::new();
-    let callbacks = get_callbacks();
+    let args = Args::parse();
+    let home_dir = dirs::home_dir().expect("expect home dir");
 
-    let history_file = dirs::home_dir()
-        .expect("expect home dir")
-        .join(HISTORY_FILE);
+    // polars elides rows and columns when printing by default, which would hide `head` output.
+    // The environment can only be changed safely before any thread starts.
+    if args.engine == Engine::Polars {
+        for var in ["POLARS_FMT_MAX_ROWS", "POLARS_FMT_MAX_COLS"] {
+            if std::env::var_os(var).is_none() {
+                std::env::set_var(var, "-1");
+            }
+        }
+    }
 
-    let mut repl = Repl::new(ctx)
-        .with_history(history_file, HISTORY_SIZE)
-        .with_banner("Welcome to Taotie, your dataset exploration REPL!")
-        .with_derived::<ReplCommand>(callbacks);
+    // commands from -c, -f or a pipe run without the REPL, and without the saved session so
+    // scripts behave the same everywhere
+    let batch = !args.commands.is_empty() || args.file.is_some() || !io::stdin().is_terminal();
+    let session_file = (!args.no_session && !batch).then(|| home_dir.join(SESSION_FILE));
+    let ctx = ReplContext::new(args.engine, session_file);
 
-    repl.run()?;
+    if batch {
+        let ret = if !args.commands.is_empty() {
+            ctx.run_commands(&args.commands, args.continue_on_error)
+        } else if let Some(file) = &args.file {
+            ctx.run_file(file, args.continue_on_error)
+        } else {
+            let mut script = String::new();
+            io::stdin().read_to_string(&mut script)?;
+            ctx.run_script(&script, args.continue_on_error)
+        };
+        std::process::exit(ret.map_or_else(|e| e.exit_code(), |_| 0));
+    }
 
-    Ok(())
+    println!("Welcome to Taotie, your dataset exploration REPL!");
+    ctx.run_repl(home_dir.join(HISTORY_FILE), HISTORY_SIZE)
 }
@@ -0,0 +1,66 @@
+use crate::{format::OutputFormat, Backend, ReplDisplay, ReplRows};
+
+pub type Rows<T> = <<T as Backend>::DataFrame as ReplDisplay>::Rows;
+
+pub struct Pending<T: Backend + ?Sized> {
+    pub rows: Rows<T>,
+    pub format: OutputFormat,
+}
+
+/// Render the first page of a result, in the given format or the session one.
+pub async fn display<T: Backend>(
+    backend: &mut T,
+    df: T::DataFrame,
+    format: Option<OutputFormat>,
+) -> anyhow::Result<String> {
+    // a new result replaces whatever was left of the previous one
+    backend.pending().take();
+    let format = format.unwrap_or(backend.settings().format);
+    let rows = df.rows().await?;
+    show_page(backend, rows, format).await
+}
+
+/// Add a small result after the main one, e.g. the stripes of an ORC file. It's rendered whole,
+/// so whatever is left of the main one stays pending for `next`. A JSON or CSV output holds a
+/// single result, so there it's left out.
+pub async fn append<T: Backend>(
+    backend: &mut T,
+    mut ret: String,
+    extra: Option<T::DataFrame>,
+    format: Option<OutputFormat>,
+) -> anyhow::Result<String> {
+    let format = format.unwrap_or(backend.settings().format);
+    let readable = matches!(
+        format,
+        OutputFormat::Table | OutputFormat::Markdown | OutputFormat::Vertical
+    );
+    if let Some(extra) = extra.filter(|_| readable) {
+        let (page, _) = extra.rows().await?.next_page(format, 0).await?;
+        ret.push('\n');
+        ret.push_str(&page);
+    }
+    Ok(ret)
+}
+
+/// Render the next page of a result. Whatever doesn't fit in `max-rows` is kept for `next` in
+/// pager mode, and otherwise counted, without keeping them, to tell how many weren't shown.
+pub async fn show_page<T: Backend>(
+    backend: &mut T,
+    mut rows: Rows<T>,
+    format: OutputFormat,
+) -> anyhow::Result<String> {
+    let settings = backend.settings().clone();
+    let (mut ret, more) = rows.next_page(format, settings.max_rows).await?;
+    if more {
+        if settings.pager {
+            ret.push_str("\n... more rows available, type `next` to show them");
+            *backend.pending() = Some(Pending { rows, format });
+        } else {
+            let rest = rows.count_rest().await?;
+            ret.push_str(&format!(
+                "\n... {rest} more rows not shown, see `set max-rows` and `set pager`"
+            ));
+        }
+    }
+    Ok(ret)
+}
@@ -0,0 +1,249 @@
+use std::sync::LazyLock;
+
+use clap::{CommandFactory, Parser};
+use datafusion::sql::parser::DFParser;
+use regex::Regex;
+
+use crate::{
+    complete::SQL_COMMANDS, Engine, ErrorKind, ReplCommand, ReplContext, ReplError, ReplMsg,
+};
+
+// a word, or a double-quoted string
+static ARG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"("[^"\n]+"|[\S]+)"#).expect("valid regex"));
+
+// the first word of the query in an unquoted `sql`/`create` statement
+static QUERY_START_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b(select|with|values)\b").expect("valid regex"));
+
+/// Split a line into arguments: words, or double-quoted strings with the quotes removed.
+pub fn split_line(line: &str) -> Vec<String> {
+    ARG_RE
+        .captures_iter(line)
+        .map(|c| c[0].replace('"', ""))
+        .collect()
+}
+
+/// Where the query starts, if `buffer` is a `sql` or `create` command with an unquoted query.
+/// Such a statement can span lines and ends with `;`.
+pub(crate) fn query_start(buffer: &str) -> Option<usize> {
+    let cmd = buffer.split_whitespace().next()?;
+    if !SQL_COMMANDS.contains(&cmd) {
+        return None;
+    }
+    let start = QUERY_START_RE.find(buffer)?.start();
+    // a quote before the query means the old `sql "select ..."` form
+    (!buffer[..start].contains('"')).then_some(start)
+}
+
+/// Whether `buffer` is a statement still waiting for its `;`.
+pub fn is_incomplete(buffer: &str) -> bool {
+    query_start(buffer).is_some() && !buffer.trim_end().ends_with(';')
+}
+
+/// The arguments of a `;`-terminated statement. The query is kept verbatim as the last one,
+/// rather than split into words and stripped of its quotes.
+fn statement_args(buffer: &str) -> Option<Vec<String>> {
+    let buffer = buffer.trim().strip_suffix(';')?;
+    let start = query_start(buffer)?;
+    let mut args = split_line(&buffer[..start]);
+    args.push(buffer[start..].trim().to_string());
+    Some(args)
+}
+
+/// Catch syntax errors before the query is sent to the backend. Only for DataFusion, whose parser
+/// this is: polars parses its own SQL dialect and reports the errors itself.
+fn check_sql(sql: &str) -> Result<(), ReplError> {
+    DFParser::parse_sql(sql).map(|_| ()).map_err(|e| {
+        ReplError::new(
+            ErrorKind::Parse,
+            e.to_string().replace("sql parser error: ", ""),
+        )
+    })
+}
+
+/// Split a script into statements. Only the line editor waits for the `;` of a SQL statement, in
+/// a script it also ends where the next command starts or the script does.
+fn statements(script: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut buffer = String::new();
+    let mut lines = script.lines().map(str::trim).peekable();
+    while let Some(line) = lines.next() {
+        if buffer.is_empty() && (line.is_empty() || line.starts_with('#')) {
+            continue;
+        }
+        if !buffer.is_empty() {
+            buffer.push('\n');
+        }
+        buffer.push_str(line);
+        if is_incomplete(&buffer) && lines.peek().is_some_and(|next| !is_command(next)) {
+            continue;
+        }
+
+        let mut statement = std::mem::take(&mut buffer);
+        if is_incomplete(&statement) {
+            statement.push(';');
+        }
+        statements.push(statement);
+    }
+    statements
+}
+
+/// Whether a line starts with a command, rather than going on with a query.
+fn is_command(line: &str) -> bool {
+    line.split_whitespace()
+        .next()
+        .is_some_and(|word| ReplCommand::command().find_subcommand(word).is_some())
+}
+
+impl ReplContext {
+    /// Run a script, one command per line, except for SQL statements which run until a `;`, the
+    /// next command or the end of the script. Blank lines and lines starting with `#` are
+    /// skipped. Errors are printed as they happen. Stops at the first failing command unless
+    /// `continue_on_error` is set, and returns the first error. A command cancelled with Ctrl-C
+    /// always stops the script.
+    pub fn run_script(&self, script: &str, continue_on_error: bool) -> Result<(), ReplError> {
+        self.run_statements(statements(script), continue_on_error)
+    }
+
+    /// Run the commands given with `-c`, each one a script of its own, so a statement ends with
+    /// its argument.
+    pub fn run_commands(
+        &self,
+        commands: &[String],
+        continue_on_error: bool,
+    ) -> Result<(), ReplError> {
+        let statements = commands.iter().flat_map(|c| statements(c)).collect();
+        self.run_statements(statements, continue_on_error)
+    }
+
+    fn run_statements(
+        &self,
+        statements: Vec<String>,
+        continue_on_error: bool,
+    ) -> Result<(), ReplError> {
+        let mut ret = Ok(());
+        for statement in statements {
+            match self.run_line(&statement) {
+                // the user wants out, not just out of this command
+                Err(e) if e.kind == ErrorKind::Cancelled => return Err(e),
+                Err(e) => {
+                    if ret.is_ok() {
+                        ret = Err(e);
+                    }
+                    if !continue_on_error {
+                        break;
+                    }
+                }
+                Ok(()) => {}
+            }
+        }
+        ret
+    }
+
+    pub fn run_file(&self, file: &str, continue_on_error: bool) -> Result<(), ReplError> {
+        match std::fs::read_to_string(file) {
+            Ok(script) => self.run_script(&script, continue_on_error),
+            Err(e) => {
+                let e = ReplError::new(ErrorKind::Io, format!("Failed to read {file}: {e}"));
+                eprintln!("{e}");
+                Err(e)
+            }
+        }
+    }
+
+    pub(crate) fn run_line(&self, line: &str) -> Result<(), ReplError> {
+        let args = statement_args(line).unwrap_or_else(|| split_line(line));
+        let args = std::iter::once(String::new()).chain(args);
+        match ReplCommand::try_parse_from(args) {
+            Ok(ReplCommand::Source(opts)) => self.run_file(&opts.file, false),
+            Ok(cmd) => {
+                let checked = match &cmd {
+                    _ if self.engine != Engine::DataFusion => Ok(()),
+                    ReplCommand::Sql(opts) => check_sql(&opts.query),
+                    ReplCommand::Create(opts) => check_sql(&opts.sql()),
+                    ReplCommand::Explain(opts) => check_sql(&opts.query),
+                    ReplCommand::Describe(opts) => opts.sql.as_deref().map_or(Ok(()), check_sql),
+                    _ => Ok(()),
+                };
+                if let Err(e) = checked {
+                    eprintln!("{e}");
+                    return Err(e);
+                }
+
+                let (msg, rx) = ReplMsg::new(cmd);
+                match self.request(msg, rx) {
+                    Ok(ret) => {
+                        println!("{ret}");
+                        Ok(())
+                    }
+                    Err(e) => {
+                        eprintln!("{e}");
+                        Err(e)
+                    }
+                }
+            }
+            Err(e) => {
+                // clap's own message comes with the usage, which is more useful than ours
+                eprintln!("{e}");
+                Err(ReplError::new(ErrorKind::Parse, e.kind().to_string()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_line_should_work() {
+        assert_eq!(
+            split_line(r#"sql "select * from t"   -n 5"#),
+            vec!["sql", "select * from t", "-n", "5"]
+        );
+    }
+
+    #[test]
+    fn statement_args_should_work() {
+        assert!(is_incomplete("sql select *\nfrom t"));
+        assert!(!is_incomplete("sql select *\nfrom t;"));
+        assert!(!is_incomplete(r#"sql "select * from t""#));
+        assert!(!is_incomplete("head t"));
+
+        assert_eq!(
+            statement_args("sql --format csv select \"A\",  'x'\nfrom t;"),
+            Some(vec![
+                "sql".to_string(),
+                "--format".to_string(),
+                "csv".to_string(),
+                "select \"A\",  'x'\nfrom t".to_string(),
+            ])
+        );
+        assert_eq!(
+            statement_args("create v --materialize as select 1;"),
+            Some(vec![
+                "create".to_string(),
+                "v".to_string(),
+                "--materialize".to_string(),
+                "as".to_string(),
+                "select 1".to_string(),
+            ])
+        );
+        assert_eq!(statement_args(r#"sql "select 1";"#), None);
+    }
+
+    #[test]
+    fn statements_should_end_without_semicolon() {
+        assert_eq!(statements("sql select 1 as a"), ["sql select 1 as a;"]);
+        assert_eq!(
+            statements("sql select *\nfrom t\ndescribe\n\nsql select 2;\nexport t out.csv"),
+            [
+                "sql select *\nfrom t;",
+                "describe",
+                "sql select 2;",
+                "export t out.csv"
+            ]
+        );
+    }
+}
@@ -0,0 +1,26 @@
+use crate::format::OutputFormat;
+use std::time::Duration;
+
+/// Options changed with the `set` command, which last for the rest of the REPL session.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub format: OutputFormat,
+    /// Rows rendered per result, or per page in pager mode. 0 renders everything.
+    pub max_rows: usize,
+    /// Keep the rows past `max_rows` for `next`. Without the pager they're only counted, and the
+    /// count is shown instead.
+    pub pager: bool,
+    /// Commands running longer than this are cancelled.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            format: OutputFormat::default(),
+            max_rows: 100,
+            pager: false,
+            timeout: None,
+        }
+    }
+}
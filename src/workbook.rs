@@ -0,0 +1,307 @@
+use anyhow::bail;
+use calamine::{open_workbook_auto, Data, DataType, Range, Reader};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::{
+    catalog::SessionCatalog,
+    cli::connect::{DatasetConn, SheetOps},
+};
+
+/// The cells of a sheet, each column typed after its cells. Both engines build their tables from
+/// it, neither reads spreadsheets on its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetTable {
+    pub names: Vec<String>,
+    pub columns: Vec<SheetColumn>,
+}
+
+/// A column of a sheet. Empty and error cells are nulls.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SheetColumn {
+    Boolean(Vec<Option<bool>>),
+    Int(Vec<Option<i64>>),
+    Float(Vec<Option<f64>>),
+    Date(Vec<Option<NaiveDate>>),
+    Datetime(Vec<Option<NaiveDateTime>>),
+    Utf8(Vec<Option<String>>),
+}
+
+/// A sheet of a workbook and the cells it uses, e.g. `A1:F200`, none if it's empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetRange {
+    pub sheet: String,
+    pub range: Option<String>,
+}
+
+/// Read a sheet of a workbook, the first one unless a sheet is given. Without a sheet, every
+/// sheet is listed with its range too, for `list` to show without opening the workbook again.
+pub fn read_sheet(opts: &SheetOps) -> anyhow::Result<(SheetTable, Vec<SheetRange>)> {
+    let mut workbook = open_workbook_auto(&opts.filename)?;
+    let names = workbook.sheet_names();
+    let sheet = match &opts.sheet {
+        Some(sheet) if names.contains(sheet) => sheet.clone(),
+        Some(sheet) => bail!(
+            "Sheet not found: {sheet}, the sheets are {}",
+            names.join(", ")
+        ),
+        None => match names.first() {
+            Some(sheet) => sheet.clone(),
+            None => bail!("{} has no sheet", opts.filename),
+        },
+    };
+    let cells = workbook.worksheet_range(&sheet)?;
+    let mut ranges = Vec::new();
+    if opts.sheet.is_none() {
+        for name in &names {
+            let range = match *name == sheet {
+                true => used_range(&cells),
+                false => used_range(&workbook.worksheet_range(name)?),
+            };
+            ranges.push(SheetRange {
+                sheet: name.clone(),
+                range,
+            });
+        }
+    }
+    let cells = match &opts.range {
+        Some(range) => {
+            let (start, end) = parse_range(range).map_err(anyhow::Error::msg)?;
+            cells.range(start, end)
+        }
+        None => cells,
+    };
+    Ok((sheet_table(&cells, opts.header), ranges))
+}
+
+fn used_range(cells: &Range<Data>) -> Option<String> {
+    let (start, end) = cells.start().zip(cells.end())?;
+    Some(format!("{}:{}", cell_name(start), cell_name(end)))
+}
+
+fn sheet_table(cells: &Range<Data>, header: bool) -> SheetTable {
+    let mut rows: Vec<&[Data]> = cells.rows().collect();
+    // a range is often drawn larger than the table
+    while rows.last().is_some_and(|r| r.iter().all(|c| c.is_empty())) {
+        rows.pop();
+    }
+    let width = cells.width();
+    let header_row = match header && !rows.is_empty() {
+        true => Some(rows.remove(0)),
+        false => None,
+    };
+
+    let mut names: Vec<String> = Vec::with_capacity(width);
+    for i in 0..width {
+        let name = match header_row.map(|r| r[i].to_string()) {
+            Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+            _ => format!("column_{}", i + 1),
+        };
+        // tables can't have two columns of the same name
+        let mut unique = name.clone();
+        let mut n = 1;
+        while names.contains(&unique) {
+            n += 1;
+            unique = format!("{name}_{n}");
+        }
+        names.push(unique);
+    }
+    let columns = (0..width)
+        .map(|i| sheet_column(rows.iter().map(|r| &r[i])))
+        .collect();
+
+    SheetTable { names, columns }
+}
+
+/// The narrowest type holding every cell of a column: whole numbers are integers, dates without a
+/// time are dates, and anything mixed is text.
+fn sheet_column<'a>(cells: impl Iterator<Item = &'a Data> + Clone) -> SheetColumn {
+    let values = cells
+        .clone()
+        .filter(|c| !matches!(c, Data::Empty | Data::Error(_)));
+    let is_datetime = |c: &Data| match c {
+        Data::DateTime(dt) => dt.is_datetime(),
+        Data::DateTimeIso(_) => c.as_datetime().is_some(),
+        _ => false,
+    };
+    let value = |c: &'a Data| (!matches!(c, Data::Empty | Data::Error(_))).then_some(c);
+
+    if values.clone().next().is_none() {
+        SheetColumn::Utf8(cells.map(|_| None).collect())
+    } else if values.clone().all(|c| matches!(c, Data::Bool(_))) {
+        SheetColumn::Boolean(cells.map(|c| value(c).and_then(|c| c.get_bool())).collect())
+    } else if values.clone().all(|c| c.as_i64().is_some() && is_whole(c)) {
+        SheetColumn::Int(cells.map(|c| value(c).and_then(|c| c.as_i64())).collect())
+    } else if values
+        .clone()
+        .all(|c| matches!(c, Data::Int(_) | Data::Float(_)))
+    {
+        SheetColumn::Float(cells.map(|c| value(c).and_then(|c| c.as_f64())).collect())
+    } else if values.clone().all(is_datetime) {
+        let datetimes: Vec<_> = cells
+            .map(|c| value(c).and_then(|c| c.as_datetime()))
+            .collect();
+        match datetimes
+            .iter()
+            .flatten()
+            .all(|dt| dt.time() == NaiveTime::MIN)
+        {
+            true => SheetColumn::Date(datetimes.iter().map(|dt| dt.map(|dt| dt.date())).collect()),
+            false => SheetColumn::Datetime(datetimes),
+        }
+    } else {
+        SheetColumn::Utf8(cells.map(|c| value(c).map(|c| c.to_string())).collect())
+    }
+}
+
+fn is_whole(cell: &Data) -> bool {
+    match cell {
+        Data::Int(_) => true,
+        Data::Float(f) => f.fract() == 0.0 && f.abs() < i64::MAX as f64,
+        _ => false,
+    }
+}
+
+/// The zero based `(row, column)` of a cell.
+type Cell = (u32, u32);
+
+/// The corners of a range like `A1:F200`.
+pub fn parse_range(range: &str) -> Result<(Cell, Cell), String> {
+    let invalid = || format!("Invalid range: {range}, expected a range like A1:F200");
+    let (start, end) = range.split_once(':').ok_or_else(invalid)?;
+    let (start, end) = (
+        parse_cell(start).ok_or_else(invalid)?,
+        parse_cell(end).ok_or_else(invalid)?,
+    );
+    if start.0 > end.0 || start.1 > end.1 {
+        return Err(invalid());
+    }
+    Ok((start, end))
+}
+
+fn parse_cell(cell: &str) -> Option<Cell> {
+    let split = cell.find(|c: char| c.is_ascii_digit())?;
+    let (letters, digits) = cell.split_at(split);
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let column = letters
+        .to_ascii_uppercase()
+        .bytes()
+        .try_fold(0u32, |n, b| {
+            n.checked_mul(26)?.checked_add((b - b'A' + 1) as u32)
+        })?;
+    let row: u32 = digits.parse().ok()?;
+    Some((row.checked_sub(1)?, column - 1))
+}
+
+/// The name of a cell, e.g. `(0, 5)` is `F1`.
+fn cell_name((row, column): Cell) -> String {
+    let mut letters = Vec::new();
+    let mut n = column + 1;
+    while n > 0 {
+        letters.push(b'A' + ((n - 1) % 26) as u8);
+        n = (n - 1) / 26;
+    }
+    letters.reverse();
+    format!("{}{}", String::from_utf8_lossy(&letters), row + 1)
+}
+
+/// The sheets of the workbooks connected without `--sheet`, with the cells each one uses, so the
+/// other sheets can be connected too. They're read when the workbook is connected, so a workbook
+/// that failed to restore isn't listed.
+pub fn unselected_sheets(catalog: &SessionCatalog) -> Option<SheetTable> {
+    let (mut datasets, mut sheets, mut ranges) = (vec![], vec![], vec![]);
+    for name in catalog.names() {
+        let Some(DatasetConn::Excel(opts)) = catalog.dataset(name) else {
+            continue;
+        };
+        if opts.sheet.is_some() {
+            continue;
+        }
+        for sheet in catalog.sheets(name) {
+            datasets.push(Some(name.to_string()));
+            sheets.push(Some(sheet.sheet.clone()));
+            ranges.push(sheet.range.clone());
+        }
+    }
+    if datasets.is_empty() {
+        return None;
+    }
+    Some(SheetTable {
+        names: vec!["dataset".into(), "sheet".into(), "range".into()],
+        columns: vec![
+            SheetColumn::Utf8(datasets),
+            SheetColumn::Utf8(sheets),
+            SheetColumn::Utf8(ranges),
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_range_should_work() {
+        assert_eq!(parse_range("A1:F200"), Ok(((0, 0), (199, 5))));
+        assert_eq!(parse_range("b3:AA4"), Ok(((2, 1), (3, 26))));
+        assert!(parse_range("A1").is_err());
+        assert!(parse_range("F1:A2").is_err());
+        assert!(parse_range("A0:B2").is_err());
+        assert_eq!(cell_name((199, 26)), "AA200");
+    }
+
+    #[test]
+    fn read_sheet_should_infer_types() -> anyhow::Result<()> {
+        let mut opts = SheetOps {
+            filename: "assets/sample.xlsx".to_string(),
+            sheet: None,
+            range: None,
+            header: true,
+        };
+        let (table, sheets) = read_sheet(&opts)?;
+        let ranges: Vec<_> = sheets
+            .iter()
+            .map(|s| (s.sheet.as_str(), s.range.as_deref()))
+            .collect();
+        assert_eq!(
+            ranges,
+            [("Sales", Some("A1:G4")), ("Summary", Some("A1:C5"))]
+        );
+        assert_eq!(
+            table.names,
+            [
+                "region",
+                "units",
+                "price",
+                "shipped",
+                "ordered",
+                "updated_at",
+                "note"
+            ]
+        );
+        assert_eq!(
+            table.columns[1],
+            SheetColumn::Int(vec![Some(10), Some(3), Some(7)])
+        );
+        assert!(matches!(table.columns[2], SheetColumn::Float(_)));
+        assert!(matches!(table.columns[3], SheetColumn::Boolean(_)));
+        assert!(matches!(table.columns[4], SheetColumn::Date(_)));
+        assert!(matches!(table.columns[5], SheetColumn::Datetime(_)));
+        assert_eq!(
+            table.columns[6],
+            SheetColumn::Utf8(vec![Some("rush".into()), None, Some("42".into())])
+        );
+
+        opts.sheet = Some("Summary".to_string());
+        opts.range = Some("B3:C10".to_string());
+        let (table, sheets) = read_sheet(&opts)?;
+        assert_eq!(table.names, ["quarter", "revenue"]);
+        assert!(sheets.is_empty());
+        assert_eq!(
+            table.columns[1],
+            SheetColumn::Float(vec![Some(1200.5), Some(980.0)])
+        );
+        Ok(())
+    }
+}